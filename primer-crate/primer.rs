@@ -6,14 +6,30 @@
 
 use std::time::Instant;
 
+mod verification;
+
 // ─── Configuration ─────────────────────────────────────────────────────────
 
 /// L1 cache segment size in bytes. 32KB is safe for virtually all x86/ARM.
 /// Each byte holds 8 bits → 8 odd numbers, so 32KB covers 262,144 odd numbers
 /// spanning ~524,288 integers per segment.
 const SEGMENT_BYTES: usize = 32 * 1024;
-const SEGMENT_BITS: u64 = (SEGMENT_BYTES * 8) as u64;
+const SEGMENT_BITS: u64 = SEGMENT_BYTES as u64 * 8;
 const SEGMENT_WORDS: usize = SEGMENT_BYTES / 8;
+/// Integers one segment spans (only odd numbers are stored).
+const SEGMENT_SPAN: u64 = SEGMENT_BITS * 2;
+
+// Standalone copy of the layout in primer_core::segment; these fail the build
+// if a hand edit breaks a derivation.
+const _: () = assert!(SEGMENT_BITS == SEGMENT_BYTES as u64 * 8);
+const _: () = assert!(SEGMENT_WORDS as u64 * 64 == SEGMENT_BITS);
+const _: () = assert!(SEGMENT_SPAN == 2 * SEGMENT_BITS);
+
+/// Largest n the sieves accept: 2^63. That keeps every half-index below
+/// 2^62, so segment ends, first-multiple offsets and 2·i + 1 all have
+/// plenty of headroom in u64. Anything bigger is rejected up front rather
+/// than left to wrap somewhere inside the segment loop.
+const MAX_N: u64 = 1 << 63;
 
 // ─── Utilities ─────────────────────────────────────────────────────────────
 
@@ -27,25 +43,43 @@ fn isqrt(n: u64) -> u64 {
     x
 }
 
+/// Last half-index whose value is ≤ `n`: n itself when odd, n − 1 when
+/// even (so 4 for n = 10: value 9, not 11). Also 0 for n = 0, where the sieves
+/// have already returned. Standalone copy of `primer_core::half`, like the
+/// segment layout above.
+#[inline]
+fn half_index_bound(n: u64) -> u64 {
+    n.saturating_sub(1) / 2
+}
+
+/// The odd value half-index `h` stands for: 2h + 1.
+#[inline]
+fn value_of_half_index(h: u64) -> u64 {
+    2 * h + 1
+}
+
 /// Upper bound on π(n) for pre-allocation. Overestimates by ~15%.
+/// Saturates at usize::MAX rather than wrapping on 32-bit targets.
 #[inline]
 fn prime_count_upper(n: u64) -> usize {
     if n < 10 { return 4; }
     let nf = n as f64;
-    (nf / nf.ln() * 1.15) as usize + 1
+    ((nf / nf.ln() * 1.15) as usize).saturating_add(1)
 }
 
 // ─── Small flat sieve (for bootstrapping primes ≤ √n) ─────────────────────
 
+/// All primes ≤ `n`, including 2. The segmented sieve only wants odd
+/// seeds and filters 2 out itself.
 fn small_sieve(n: u64) -> Vec<u64> {
     if n < 2 { return vec![]; }
-    let h = n / 2;
+    let h = half_index_bound(n);
     let num_words = ((h >> 6) + 1) as usize;
     let mut b = vec![!0u64; num_words];
     b[0] ^= 1;
     let sqrt_n = isqrt(n);
 
-    for i in 1..=(sqrt_n / 2) {
+    for i in 1..=half_index_bound(sqrt_n) {
         if (b[(i >> 6) as usize] >> (i & 63)) & 1 == 1 {
             let step = 2 * i + 1;
             let mut j = 2 * i * (i + 1);
@@ -59,13 +93,11 @@ fn small_sieve(n: u64) -> Vec<u64> {
     let mut r = Vec::with_capacity(prime_count_upper(n));
     r.push(2);
     for (i, &word) in b.iter().enumerate() {
-        let base = ((i << 6) * 2 + 1) as u64;
-        if base > n { break; }
+        if value_of_half_index((i << 6) as u64) > n { break; }
         let mut w = word;
         while w != 0 {
-            let tz = w.trailing_zeros() as usize;
-            let p = ((i << 6) + tz) * 2 + 1;
-            if (p as u64) <= n { r.push(p as u64); }
+            let p = value_of_half_index(((i << 6) as u64) + w.trailing_zeros() as u64);
+            if p <= n { r.push(p); }
             w &= w - 1;
         }
     }
@@ -83,12 +115,28 @@ fn small_sieve(n: u64) -> Vec<u64> {
 // The segment buffer stays hot in L1 cache, eliminating the thrashing
 // that kills flat sieves when the bit array exceeds ~32KB.
 
+/// Half-index of the first odd multiple of `p` that needs striking in a
+/// segment starting at half-index `lo`: p² itself, or the first multiple
+/// at or after `lo`. Stays in range for every lo ≤ MAX_N / 2.
+#[inline]
+fn first_strike(p: u64, lo: u64) -> u64 {
+    let start_half = (p * p - 1) / 2;
+    if start_half >= lo {
+        start_half
+    } else {
+        let offset = (lo - start_half) % p;
+        if offset == 0 { lo } else { lo + (p - offset) }
+    }
+}
+
+/// All primes ≤ `n`. Panics if `n` exceeds `MAX_N` (2^63).
 pub fn segmented_sieve(n: u64) -> Vec<u64> {
+    assert!(n <= MAX_N, "segmented_sieve: n = {} exceeds the supported maximum 2^63", n);
     if n < 2 { return vec![]; }
     if n < 3 { return vec![2]; }
 
     let sqrt_n = isqrt(n);
-    let h = n / 2; // max half-index (odd-only)
+    let h = half_index_bound(n); // last half-index with value ≤ n
 
     // Phase 1: bootstrap sieving primes ≤ √n
     let small_primes = small_sieve(sqrt_n);
@@ -98,7 +146,9 @@ pub fn segmented_sieve(n: u64) -> Vec<u64> {
     let mut result = Vec::with_capacity(prime_count_upper(n));
     result.push(2);
 
-    let mut seg = vec![0u64; SEGMENT_WORDS];
+    // Never allocate more than the range needs: for small n the whole
+    // range is a fraction of one segment.
+    let mut seg = vec![0u64; std::cmp::min(SEGMENT_WORDS as u64, h / 64 + 1) as usize];
     let mut lo: u64 = 0;
 
     while lo <= h {
@@ -118,16 +168,7 @@ pub fn segmented_sieve(n: u64) -> Vec<u64> {
 
         // Strike composites for each sieving prime
         for &p in &small_odd {
-            let start_half = (p * p - 1) / 2;
-
-            let first = if start_half >= lo {
-                start_half
-            } else {
-                let offset = (lo - start_half) % p;
-                if offset == 0 { lo } else { lo + p - offset }
-            };
-
-            let mut j = first;
+            let mut j = first_strike(p, lo);
             while j <= hi {
                 let local = (j - lo) as usize;
                 seg[local >> 6] &= !(1u64 << (local & 63));
@@ -145,9 +186,10 @@ pub fn segmented_sieve(n: u64) -> Vec<u64> {
         for (wi, &word) in seg[..words_needed].iter().enumerate() {
             let mut w = word;
             while w != 0 {
-                let tz = w.trailing_zeros() as usize;
-                let half_idx = lo as usize + (wi << 6) + tz;
-                let p = (half_idx * 2 + 1) as u64;
+                let tz = w.trailing_zeros() as u64;
+                // u64 throughout: `lo as usize` would truncate on 32-bit targets
+                let half_idx = lo + ((wi as u64) << 6) + tz;
+                let p = value_of_half_index(half_idx);
                 if p <= n {
                     result.push(p);
                 }
@@ -167,7 +209,8 @@ fn main() {
     let n = 500_000;
 
     println!("🦀 Segmented Bit-Packed Sieve of Eratosthenes 🦀\n");
-    println!("Segment size: {} KB (L1 cache line)", SEGMENT_BYTES / 1024);
+    println!("Segment size: {} KB ({} u64 words, {} odd numbers spanning {} integers)",
+             SEGMENT_BYTES / 1024, SEGMENT_WORDS, SEGMENT_BITS, SEGMENT_SPAN);
 
     let start = Instant::now();
     let primes = segmented_sieve(n);
@@ -182,14 +225,11 @@ fn main() {
     println!("\nFirst 10 primes: {:?}", &primes[..10]);
     println!("Last 10 primes:  {:?}", &primes[primes.len() - 10..]);
 
-    // Verify known values
-    assert_eq!(primes.len(), 41_538);
-    assert_eq!(primes[9], 29);
-    assert_eq!(primes[9_999], 104_729);
-    assert_eq!(primes[10_000], 104_743);
-    assert_eq!(*primes.last().unwrap(), 499_979);
+    // Verify against the published reference tables
+    let checked = verification::verify_against_tables(6, segmented_sieve)
+        .unwrap_or_else(|e| panic!("reference check failed: {}", e));
 
-    println!("\n✓ All assertions passed!");
+    println!("\n✓ All assertions passed! ({} reference values checked)", checked);
 }
 
 #[cfg(test)]
@@ -218,6 +258,45 @@ mod tests {
         assert_eq!(segmented_sieve(3), vec![2, 3]);
     }
 
+    #[test]
+    fn test_small_sieve_tiny_limits() {
+        assert_eq!(small_sieve(0), vec![]);
+        assert_eq!(small_sieve(1), vec![]);
+        assert_eq!(small_sieve(2), vec![2]);
+        assert_eq!(small_sieve(3), vec![2, 3]);
+        assert_eq!(small_sieve(4), vec![2, 3]);
+        assert_eq!(small_sieve(5), vec![2, 3, 5]);
+    }
+
+    #[test]
+    fn test_two_emitted_exactly_once() {
+        for n in 2..=1_000 {
+            let p = segmented_sieve(n);
+            assert_eq!(p.iter().filter(|&&x| x == 2).count(), 1, "n={}", n);
+            assert_eq!(p, small_sieve(n), "n={}", n);
+        }
+    }
+
+    /// Trial division, deliberately sharing nothing with either sieve.
+    fn naive_primes(n: u64) -> Vec<u64> {
+        (2..=n).filter(|&k| (2..k).take_while(|d| d * d <= k).all(|d| k % d != 0)).collect()
+    }
+
+    #[test]
+    fn test_matches_naive_small_n() {
+        for n in 0..=2_000 {
+            assert_eq!(segmented_sieve(n), naive_primes(n), "n={}", n);
+        }
+    }
+
+    #[test]
+    fn test_two_segment_boundaries() {
+        for &n in &[2 * SEGMENT_BITS, 2 * SEGMENT_BITS + 1] {
+            let p = segmented_sieve(n);
+            assert_eq!(p, naive_primes(n), "n={}", n);
+        }
+    }
+
     #[test]
     fn test_boundary_primes() {
         let p = segmented_sieve(29);
@@ -227,6 +306,61 @@ mod tests {
         assert_eq!(*p.last().unwrap(), 499_979);
     }
 
+    #[test]
+    fn test_reference_tables() {
+        assert_eq!(verification::verify_against_tables(7, segmented_sieve), Ok(43));
+    }
+
+    #[test]
+    fn test_reference_tables_catch_missing_prime() {
+        fn drops_one(n: u64) -> Vec<u64> {
+            segmented_sieve(n).into_iter().filter(|&p| p != 7_919).collect()
+        }
+        assert!(verification::verify_against_tables(4, drops_one).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "exceeds the supported maximum")]
+    fn test_rejects_n_above_cap() {
+        segmented_sieve(MAX_N + 1);
+    }
+
+    #[test]
+    fn test_prime_count_upper_at_extremes() {
+        assert!(prime_count_upper(MAX_N) > 0);
+        assert!(prime_count_upper(u64::MAX) > 0);
+        assert!(prime_count_upper(1_000_000) >= 78_498);
+    }
+
+    #[test]
+    fn test_half_index_bound() {
+        for n in 1..1_000u64 {
+            let h = half_index_bound(n);
+            assert!(value_of_half_index(h) <= n && value_of_half_index(h + 1) > n, "n={}", n);
+        }
+        assert_eq!(value_of_half_index(half_index_bound(10)), 9);
+        assert_eq!(value_of_half_index(half_index_bound(11)), 11);
+        assert_eq!(value_of_half_index(half_index_bound(MAX_N)), MAX_N - 1);
+        assert_eq!(value_of_half_index(half_index_bound(u64::MAX)), u64::MAX);
+    }
+
+    #[test]
+    fn test_first_strike_near_cap() {
+        // Largest seed the cap allows (the largest prime ≤ √(2^63)) against
+        // segment starts right up to the top of the half-index range.
+        let p = 3_037_000_493;
+        let top = half_index_bound(MAX_N);
+        for lo in [top - SEGMENT_BITS, top - p, top - 1, top] {
+            let j = first_strike(p, lo);
+            assert!(j >= lo && j - lo < p, "lo={}", lo);
+            assert_eq!((2 * j + 1) % p, 0, "lo={}", lo);
+        }
+        // Below p² the first strike is p² itself
+        assert_eq!(first_strike(7, 0), 24);
+        assert_eq!(first_strike(7, 24), 24);
+        assert_eq!(first_strike(7, 25), 31);
+    }
+
     #[test]
     fn test_isqrt_safety() {
         assert_eq!(isqrt(0), 0);
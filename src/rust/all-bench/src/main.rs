@@ -1,184 +1,70 @@
 //! Prime Sieve Benchmark Harness v2
-//! Compares: wofl bit-packed sieve vs wofl SEGMENTED sieve vs `primes` crate vs `primal` crate
+//! Compares: wofl bit-packed sieve vs wofl SEGMENTED sieve vs wofl atomic flat sieve
+//! vs wofl prime_pi (count only) vs `primes` crate vs `primal` crate
 //!
-//! Usage: cargo run --release
-
-use std::time::{Duration, Instant};
-use std::fmt;
-
-// ─── Shared utilities ──────────────────────────────────────────────────────
-
-#[inline]
-fn isqrt(n: u64) -> u64 {
-    if n == 0 { return 0; }
-    let mut x = (n as f64).sqrt() as u64;
-    while x > 0 && x.checked_mul(x).map_or(true, |sq| sq > n) { x -= 1; }
-    while (x + 1).checked_mul(x + 1).map_or(false, |sq| sq <= n) { x += 1; }
-    x
-}
-
-#[inline]
-fn prime_count_upper(n: u64) -> usize {
-    if n < 10 { return 4; }
-    let nf = n as f64;
-    (nf / nf.ln() * 1.15) as usize + 1
-}
-
-// ─── wofl's bit-packed sieve (original, non-segmented) ─────────────────────
-
-fn wofl_sieve(n: u64) -> Vec<u64> {
-    if n < 2 { return vec![]; }
-    let h = n / 2;
-    let num_words = ((h >> 6) + 1) as usize;
-    let mut b = vec![!0u64; num_words];
-    b[0] ^= 1;
-    let sqrt_n = isqrt(n);
-
-    for i in 1..=(sqrt_n / 2) {
-        if (b[(i >> 6) as usize] >> (i & 63)) & 1 == 1 {
-            let step = 2 * i + 1;
-            let mut j = 2 * i * (i + 1);
-            while j <= h {
-                b[(j >> 6) as usize] &= !(1u64 << (j & 63));
-                j += step;
-            }
-        }
-    }
-
-    let mut r = Vec::with_capacity(prime_count_upper(n));
-    r.push(2);
-    for (i, &word) in b.iter().enumerate() {
-        let base = ((i << 6) * 2 + 1) as u64;
-        if base > n { break; }
-        let mut w = word;
-        while w != 0 {
-            let tz = w.trailing_zeros() as usize;
-            let p = ((i << 6) + tz) * 2 + 1;
-            if (p as u64) <= n { r.push(p as u64); }
-            w &= w - 1;
-        }
-    }
-    r
-}
-
-// ─── wofl's SEGMENTED bit-packed sieve (L1-cache-friendly) ─────────────────
-//
-// Strategy:
-//   1. Small sieve: find all primes up to √n (fits in a few KB)
-//   2. Process the full range in L1-sized segments (~32KB = 262144 odd numbers)
-//   3. For each segment, strike out composites using the small primes
-//   4. Extract surviving primes via Brian Kernighan bit iteration
-//
-// The segment buffer stays hot in L1 cache the entire time, eliminating
-// the cache thrashing that kills the flat sieve at large n.
-
-/// L1 cache segment size in bytes. 32KB is safe for virtually all x86/ARM.
-/// Each byte holds 8 bits → 8 odd numbers, so 32KB covers 262,144 odd numbers
-/// which spans ~524,288 integers.
-const SEGMENT_BYTES: usize = 32 * 1024;
-const SEGMENT_BITS: u64 = (SEGMENT_BYTES * 8) as u64;
-const SEGMENT_WORDS: usize = SEGMENT_BYTES / 8;
-
-fn wofl_segmented_sieve(n: u64) -> Vec<u64> {
-    if n < 2 { return vec![]; }
-    if n < 3 { return vec![2]; }
-
-    let sqrt_n = isqrt(n);
-    let h = n / 2; // max half-index (odd-only)
-
-    // ── Phase 1: small sieve to find primes ≤ √n ──────────────────────
-    // These are the "sieving primes" that we'll use to mark composites
-    // in each segment. For n=50M, √n ≈ 7071, so this is tiny.
-    let small_primes = wofl_sieve(sqrt_n);
-
-    // Pre-compute sieving state for each small odd prime:
-    //   - half_prime: the step size in half-index space (= prime value)
-    //   - start offset gets computed per-segment
-    // Skip prime=2 since we only track odd numbers
-    let small_odd_primes: Vec<u64> = small_primes.iter()
-        .copied()
-        .filter(|&p| p > 2)
-        .collect();
-
-    // ── Phase 2: process segments ──────────────────────────────────────
-    let mut result = Vec::with_capacity(prime_count_upper(n));
-    result.push(2);
-
-    // Segment buffer — reused across all segments, stays in L1
-    let mut seg = vec![0u64; SEGMENT_WORDS];
-
-    // Process in chunks of SEGMENT_BITS half-indices
-    let mut lo: u64 = 0; // current segment start (in half-index space)
-
-    while lo <= h {
-        let hi = std::cmp::min(lo + SEGMENT_BITS - 1, h); // inclusive end
-        let seg_len = (hi - lo + 1) as usize; // actual bits in this segment
-        let words_needed = (seg_len + 63) / 64;
-
-        // Reset segment: all bits = 1 (assume prime)
-        for w in seg[..words_needed].iter_mut() {
-            *w = !0u64;
-        }
-
-        // Special case: bit 0 of first segment represents 1 (not prime)
-        if lo == 0 {
-            seg[0] ^= 1;
-        }
-
-        // Strike composites for each sieving prime
-        for &p in &small_odd_primes {
-            // Find the first half-index ≥ lo that's a composite of p.
-            // Number at half-index lo is: 2*lo + 1
-            // We want the smallest odd multiple of p that's ≥ max(p*p, 2*lo+1)
-            let start_half = (p * p - 1) / 2;
-
-            // Find first composite half-index ≥ lo
-            let first = if start_half >= lo {
-                start_half
-            } else {
-                // Find remainder and adjust
-                let offset = (lo - start_half) % p;
-                if offset == 0 { lo } else { lo + p - offset }
-            };
-
-            // Strike all multiples within this segment
-            let mut j = first;
-            while j <= hi {
-                let local = (j - lo) as usize;
-                seg[local >> 6] &= !(1u64 << (local & 63));
-                j += p;
-            }
-        }
-
-        // Mask off any trailing bits beyond h in the last segment
-        if hi == h && seg_len % 64 != 0 {
-            let last_word = words_needed - 1;
-            let valid_bits = seg_len % 64;
-            seg[last_word] &= (1u64 << valid_bits) - 1;
-        }
-
-        // Extract primes from this segment
-        for (wi, &word) in seg[..words_needed].iter().enumerate() {
-            let mut w = word;
-            while w != 0 {
-                let tz = w.trailing_zeros() as usize;
-                let half_idx = lo as usize + (wi << 6) + tz;
-                let p = (half_idx * 2 + 1) as u64;
-                if p <= n {
-                    result.push(p);
-                }
-                w &= w - 1;
-            }
-        }
-
-        lo += SEGMENT_BITS;
-    }
-
-    result
+//! Usage: cargo run --release [-- --units ns|us|ms|s|auto] [--validate off|counts|hashes|full] [--warmup N]
+//!                              [--digit-separator comma|dot|space|thin|underscore|apostrophe|none]
+//!                              [--sizes N...] [--noise-threshold PCT] [--max-reruns R]
+//!                              [--reuse-comparisons] [--energy]
+//!
+//! `--reuse-comparisons` runs the iterator-based comparison crates once, to
+//! the largest n, and reports split times at each smaller n. Those rows are
+//! estimates and are labelled "(est.)"; measured mode is the default.
+//!
+//! `--sizes` replaces the default list of n; each size may be digit-grouped
+//! (`--sizes 1,000,000 50_000_000`).
+//!
+//! Rows whose MAD / median exceeds `--noise-threshold` percent (default 5;
+//! `5` and `5%` both work) are re-run up to `--max-reruns` times (default
+//! 2); the steadiest sample set is kept and the row is annotated.
+//!
+//! `--warmup` sets the untimed calls made before each implementation's timed
+//! runs (default 2, 0 allowed). With warmup on, each section also shows the
+//! first call against the steady-state median.
+//!
+//! `--validate` sets how hard the outputs are cross-checked once each size's
+//! timed runs are done (default `counts`); `full` names the first index at
+//! which an implementation diverges from the flat sieve. The prime_pi row
+//! has only a count to check, so it is held to `counts` at most.
+//!
+//! `--energy` adds median energy per run and primes per joule from the
+//! Linux RAPL package counter, less an idle baseline taken at startup.
+//! It needs `--features energy` and read access to the counter; otherwise
+//! the harness says why and runs without those columns.
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
+
+use benchkit::alloc::{self, CountingAlloc};
+use benchkit::cli::CommonArgs;
+use benchkit::energy;
+use benchkit::scaling::PowerFit;
+use benchkit::segment;
+use benchkit::splits::{bench_splits, Split, SplitTimer};
+use benchkit::validate::{self, Level};
+use benchkit::{bench, bench_count, BenchResult, Bytes, Grouping, Percent, Ratio, RetryPolicy, SpeedupMatrix, TablePlan};
+use primer_core::{flat_atomic_sieve, prime_pi, segmented_sieve, sieve_primes};
+
+// Every allocation is counted, so the memory columns are measured peaks.
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+// ─── wofl's ATOMIC flat sieve (experimental, multi-threaded) ──────────────
+
+/// `primer_core::flat_atomic_sieve`, panicking on a worker failure so the
+/// bench loop sees a plain sieve.
+fn wofl_flat_atomic_sieve(n: u64, threads: usize) -> Vec<u64> {
+    flat_atomic_sieve(n, threads)
+        .unwrap_or_else(|e| panic!("wofl_flat_atomic_sieve: n = {}: {}", n, e))
 }
 
 // ─── Wrappers for crate implementations ────────────────────────────────────
 
+/// Past this the primes crate takes too long to be worth running.
+const PRIMES_CRATE_MAX_N: u64 = 1_000_000;
+
 fn primes_crate_sieve(n: u64) -> Vec<u64> {
     use primes::{Sieve, PrimeSet};
     let mut sieve = Sieve::new();
@@ -192,6 +78,24 @@ fn primal_crate_sieve(n: u64) -> Vec<u64> {
         .collect()
 }
 
+/// `primes_crate_sieve` run to the largest threshold, with a split at each.
+fn primes_crate_splits(thresholds: &[u64]) -> (Vec<u64>, Vec<Split>) {
+    use primes::{Sieve, PrimeSet};
+    let max = *thresholds.last().unwrap();
+    let mut sieve = Sieve::new();
+    let mut timer = SplitTimer::new(sieve.iter(), thresholds);
+    let primes = timer.by_ref().take_while(|&p| p <= max).collect();
+    (primes, timer.finish())
+}
+
+/// `primal_crate_sieve` run to the largest threshold, with a split at each.
+fn primal_crate_splits(thresholds: &[u64]) -> (Vec<u64>, Vec<Split>) {
+    let max = *thresholds.last().unwrap();
+    let mut timer = SplitTimer::new(primal::Primes::all().map(|p| p as u64), thresholds);
+    let primes = timer.by_ref().take_while(|&p| p <= max).collect();
+    (primes, timer.finish())
+}
+
 fn primal_crate_sieve_direct(n: u64) -> Vec<u64> {
     let sieve = primal::Sieve::new(n as usize);
     sieve.primes_from(0)
@@ -200,262 +104,513 @@ fn primal_crate_sieve_direct(n: u64) -> Vec<u64> {
         .collect()
 }
 
-// ─── Benchmarking machinery ────────────────────────────────────────────────
+// ─── Report rendering ──────────────────────────────────────────────────────
+//
+// Everything printed by main() goes through these pure functions so the
+// table layout can be pinned by tests instead of by eyeballing a full run.
+
+const SECTION_RULE: &str = "──────────────────────────────────────────────────────────────────────────────────";
 
-#[allow(dead_code)]
-struct BenchResult {
-    name: String,
+fn render_section_open(n: u64, iterations: usize, digits: Grouping) -> String {
+    format!("┌─ n = {} ({} iterations) ─────────────────────────────────────────────────────\n│\n",
+        digits.format(n), iterations)
+}
+
+/// Inputs for the per-n summary block: the agreed prime count, the median
+/// of every implementation that ran, and the wofl variants to compare
+/// pairwise.
+struct Summary<'a> {
     n: u64,
     prime_count: usize,
-    times: Vec<Duration>,
-    sieve_bytes: usize,
-    result_bytes: usize,
+    medians: Vec<(&'a str, Duration)>,
+    variants: Vec<(&'a str, Duration)>,
+    digits: Grouping,
 }
 
-impl BenchResult {
-    fn median(&self) -> Duration {
-        let mut sorted: Vec<Duration> = self.times.clone();
-        sorted.sort();
-        sorted[sorted.len() / 2]
-    }
+fn render_summary(s: &Summary) -> String {
+    let mut out = String::new();
+    let fastest = s.medians.iter().map(|(_, d)| *d).min().unwrap();
 
-    fn min(&self) -> Duration {
-        *self.times.iter().min().unwrap()
-    }
+    out.push_str("│\n");
+    out.push_str(&format!("│  π({}) = {}   │  All implementations agree ✓\n",
+        s.digits.format(s.n), s.digits.format(s.prime_count as u64)));
 
-    fn max(&self) -> Duration {
-        *self.times.iter().max().unwrap()
+    for (name, time) in &s.medians {
+        let ratio = Ratio::of(*time, fastest);
+        match ratio.0 {
+            Some(r) if r <= 1.01 => out.push_str(&format!("│  {:16} : fastest 🏆\n", name)),
+            Some(_) => out.push_str(&format!("│  {:16} : {} slower\n", name, ratio)),
+            None => out.push_str(&format!("│  {:16} : {}\n", name, ratio)),
+        }
     }
 
-    fn mean(&self) -> Duration {
-        let total: Duration = self.times.iter().sum();
-        total / self.times.len() as u32
+    // Every wofl variant against every other
+    if s.variants.len() > 1 {
+        out.push_str("│\n");
+        out.push_str("│  Speedup (row vs column):\n");
+        for line in SpeedupMatrix::new(&s.variants).render().lines() {
+            out.push_str(&format!("│    {}\n", line));
+        }
     }
 
-    fn stddev_us(&self) -> f64 {
-        let mean = self.mean().as_nanos() as f64;
-        let variance = self.times.iter()
-            .map(|t| {
-                let diff = t.as_nanos() as f64 - mean;
-                diff * diff
-            })
-            .sum::<f64>() / self.times.len() as f64;
-        variance.sqrt() / 1000.0
-    }
+    out.push_str("│\n");
+    out.push_str(&format!("└{}\n\n", SECTION_RULE));
+    out
 }
 
-impl fmt::Display for BenchResult {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:<24} │ {:>10} │ {:>10} │ {:>10} │ {:>10} │ {:>8.1} │ {:>8} │ {:>8}",
-            self.name,
-            format_duration(self.min()),
-            format_duration(self.median()),
-            format_duration(self.mean()),
-            format_duration(self.max()),
-            self.stddev_us(),
-            format_bytes(self.sieve_bytes),
-            format_bytes(self.result_bytes),
-        )
+/// The fit behind an extrapolated row. Only measured medians count: an
+/// "(est.)" split time in the fit would let one estimate feed the next.
+/// `None` with fewer than two measured sizes.
+fn measured_fit(history: &[(u64, Duration, bool)]) -> Option<PowerFit> {
+    let measured: Vec<(u64, Duration)> = history.iter()
+        .filter(|&&(_, _, measured)| measured)
+        .map(|&(n, t, _)| (n, t))
+        .collect();
+    if measured.len() < 2 {
+        return None;
     }
+    PowerFit::fit(&measured)
 }
 
-fn format_duration(d: Duration) -> String {
-    let nanos = d.as_nanos();
-    if nanos < 1_000 {
-        format!("{} ns", nanos)
-    } else if nanos < 1_000_000 {
-        format!("{:.1} µs", nanos as f64 / 1_000.0)
-    } else if nanos < 1_000_000_000 {
-        format!("{:.2} ms", nanos as f64 / 1_000_000.0)
-    } else {
-        format!("{:.2} s", nanos as f64 / 1_000_000_000.0)
-    }
+fn render_memory_report(n: u64, flat_sieve_bytes: usize, seg_sieve_bytes: usize, result_bytes: usize,
+                        digits: Grouping) -> String {
+    let mut out = String::new();
+    out.push_str(&format!("📊 Memory Efficiency @ n={} (measured peaks)\n", digits.format(n)));
+    out.push_str("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━\n");
+    out.push_str(&format!("  flat sieve array:     {:>10}  (entire range in memory)\n", Bytes(flat_sieve_bytes as u64)));
+    out.push_str(&format!("  segmented buffer:     {:>10}  (one L1 segment + seed primes) 🏆\n", Bytes(seg_sieve_bytes as u64)));
+    out.push_str(&format!("  result vec (shared):  {:>10}\n", Bytes(result_bytes as u64)));
+    out.push_str(&format!("  flat total:           {:>10}\n", Bytes((flat_sieve_bytes + result_bytes) as u64)));
+    out.push_str(&format!("  segmented total:      {:>10}\n", Bytes((seg_sieve_bytes + result_bytes) as u64)));
+    out.push_str(&format!("  sieve memory saving:  {:>10.0}x\n", flat_sieve_bytes as f64 / seg_sieve_bytes as f64));
+    out
 }
 
-fn format_bytes(b: usize) -> String {
-    if b < 1024 {
-        format!("{} B", b)
-    } else if b < 1024 * 1024 {
-        format!("{:.1} KB", b as f64 / 1024.0)
-    } else {
-        format!("{:.1} MB", b as f64 / (1024.0 * 1024.0))
-    }
+// ─── Command line ──────────────────────────────────────────────────────────
+
+const USAGE: &str = "usage: seg [--units ns|us|ms|s|auto] [--validate off|counts|hashes|full] [--warmup N]
+           [--digit-separator comma|dot|space|thin|underscore|apostrophe|none] [--sizes N...]
+           [--noise-threshold PCT] [--max-reruns R] [--reuse-comparisons] [--energy]";
+
+const DEFAULT_SIZES: [u64; 7] = [
+    10_000,
+    100_000,
+    500_000,
+    1_000_000,
+    10_000_000,
+    50_000_000,
+    100_000_000,
+];
+
+struct Args {
+    common: CommonArgs,
+    validate: Level,
+    retry: RetryPolicy,
+    reuse_comparisons: bool,
+    energy: bool,
 }
 
-fn bench<F>(name: &str, n: u64, iterations: usize, sieve_bytes: usize, f: F) -> BenchResult
-where
-    F: Fn(u64) -> Vec<u64>,
-{
-    // Warmup
-    let _ = f(n);
-    let _ = f(n);
-
-    let mut times = Vec::with_capacity(iterations);
-    let mut prime_count = 0;
-    let mut result_bytes = 0;
-
-    for _ in 0..iterations {
-        let start = Instant::now();
-        let result = f(n);
-        let elapsed = start.elapsed();
-        prime_count = result.len();
-        result_bytes = result.capacity() * std::mem::size_of::<u64>();
-        times.push(elapsed);
-        std::hint::black_box(&result);
-    }
-
-    BenchResult {
-        name: name.to_string(),
-        n,
-        prime_count,
-        times,
-        sieve_bytes,
-        result_bytes,
+fn parse_args<I: Iterator<Item = String>>(args: I) -> Result<Args, String> {
+    let mut parsed = Args {
+        common: CommonArgs::new(&DEFAULT_SIZES),
+        validate: Level::default(),
+        retry: RetryPolicy::default(),
+        reuse_comparisons: false,
+        energy: false,
+    };
+    let mut args = args.peekable();
+    while let Some(arg) = args.next() {
+        if parsed.common.parse_flag(&arg, &mut args)? {
+            continue;
+        }
+        match arg.as_str() {
+            "--validate" => {
+                let value = args.next().ok_or("--validate needs a value")?;
+                parsed.validate = value.parse()?;
+            }
+            "--reuse-comparisons" => parsed.reuse_comparisons = true,
+            "--energy" => parsed.energy = true,
+            "--noise-threshold" => {
+                let value = args.next().ok_or("--noise-threshold needs a value")?;
+                let pct: Percent = value.parse()
+                    .ok()
+                    .filter(|p: &Percent| p.0 >= 0.0)
+                    .ok_or_else(|| format!("invalid noise threshold '{}'", value))?;
+                parsed.retry.threshold = pct.0;
+            }
+            "--max-reruns" => {
+                let value = args.next().ok_or("--max-reruns needs a value")?;
+                parsed.retry.max_reruns = value.parse()
+                    .map_err(|_| format!("invalid re-run count '{}'", value))?;
+            }
+            other => return Err(format!("unknown argument '{}'", other)),
+        }
     }
+    Ok(parsed)
 }
 
-fn print_header() {
-    println!("{:<24} │ {:>10} │ {:>10} │ {:>10} │ {:>10} │ {:>8} │ {:>8} │ {:>8}",
-        "Implementation", "Min", "Median", "Mean", "Max", "σ (µs)", "Sieve", "Result");
-    println!("{}", "─".repeat(115));
-}
-
-fn main() {
-    println!("🦀 Prime Sieve Benchmark v2 — now with segmented sieve! 🦀");
-    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-    println!("  Segment size: {} KB (L1 cache line)", SEGMENT_BYTES / 1024);
-    println!();
-
-    let test_sizes: Vec<u64> = vec![
-        10_000,
-        100_000,
-        500_000,
-        1_000_000,
-        10_000_000,
-        50_000_000,
-        100_000_000,
-    ];
+fn main() -> io::Result<()> {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("error: {}\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    writeln!(out, "🦀 Prime Sieve Benchmark v2 — now with segmented sieve! 🦀")?;
+    writeln!(out, "━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━")?;
+    writeln!(out, "  Segment size: {}", segment::describe(args.common.digits))?;
+    let threads = thread::available_parallelism().map_or(1, |t| t.get());
+    writeln!(out, "  Atomic sieve threads: {}", threads)?;
+    if args.energy {
+        match energy::enable() {
+            Ok(meter) => writeln!(out, "  Energy: RAPL package counter, idle baseline {:.2} W subtracted",
+                meter.idle_microwatts() as f64 / 1e6)?,
+            Err(reason) => writeln!(out, "  Energy: unavailable ({})", reason)?,
+        }
+    }
+    writeln!(out)?;
 
     let iterations = 25;
 
-    for &n in &test_sizes {
-        println!("┌─ n = {} ({} iterations) ─────────────────────────────────────────────────────",
-            format_with_commas(n), iterations);
-        println!("│");
+    // Below --validate hashes only counts are checked, so no row needs to
+    // hold on to its primes
+    let trim = |r: BenchResult| if args.validate.reads_output() { r } else { r.without_output() };
+
+    // With --reuse-comparisons the iterator crates run once per iteration to
+    // the largest n, and every section takes its split-time estimate.
+    let mut primes_estimates = BTreeMap::new();
+    let mut primal_estimates = BTreeMap::new();
+    if args.reuse_comparisons {
+        let mut thresholds = args.common.sizes.clone();
+        thresholds.sort();
+        thresholds.dedup();
+        let primes_thresholds: Vec<u64> = thresholds.iter().copied().filter(|&n| n <= PRIMES_CRATE_MAX_N).collect();
+        if !primes_thresholds.is_empty() {
+            for r in bench_splits("primes crate (iter)", &primes_thresholds, iterations, args.common.warmup, primes_crate_splits) {
+                primes_estimates.insert(r.n, trim(r));
+            }
+        }
+        for r in bench_splits("primal (iterator)", &thresholds, iterations, args.common.warmup, primal_crate_splits) {
+            primal_estimates.insert(r.n, trim(r));
+        }
+    }
 
-        print_header();
+    // Medians the primes crate did record, for extrapolating past its
+    // cutoff, each marked measured or "(est.)"
+    let mut primes_history: Vec<(u64, Duration, bool)> = Vec::new();
+
+    for &n in &args.common.sizes {
+        write!(out, "{}", render_section_open(n, iterations, args.common.digits))?;
 
         // wofl flat sieve
-        let wofl_flat_mem = ((n / 2 / 64 + 1) * 8) as usize;
-        let wofl_flat = bench("wofl (flat)", n, iterations, wofl_flat_mem, wofl_sieve);
-        println!("{}", wofl_flat);
+        let wofl_flat = trim(args.retry.run(|| bench("wofl (flat)", n, iterations, args.common.warmup, sieve_primes)));
 
         // wofl segmented sieve
-        let wofl_seg_mem = SEGMENT_BYTES; // only ever uses one segment buffer
-        let wofl_seg = bench("wofl (segmented)", n, iterations, wofl_seg_mem, wofl_segmented_sieve);
-        println!("{}", wofl_seg);
+        let wofl_seg = trim(args.retry.run(|| bench("wofl (segmented)", n, iterations, args.common.warmup, segmented_sieve)));
+
+        // wofl prime_pi — the segmented sieve, counting instead of collecting
+        let wofl_pi = args.retry.run(|| bench_count("wofl (prime_pi)", n, iterations, args.common.warmup, prime_pi));
 
-        // primes crate (skip for large n — it's painfully slow)
-        let primes_res = if n <= 1_000_000 {
-            Some(bench("primes crate (iter)", n, iterations, 0, primes_crate_sieve))
+        // wofl atomic flat sieve (experimental) — same bitmap as flat
+        let wofl_atomic = trim(args.retry.run(|| bench("wofl (flat atomic)", n, iterations, args.common.warmup,
+            |n| wofl_flat_atomic_sieve(n, threads))));
+
+        // primes crate (skip for large n — it's painfully slow). A size
+        // repeated in --sizes has no estimate left and is measured instead.
+        let primes_res = if n <= PRIMES_CRATE_MAX_N {
+            Some(primes_estimates.remove(&n).unwrap_or_else(||
+                trim(args.retry.run(|| bench("primes crate (iter)", n, iterations, args.common.warmup, primes_crate_sieve).unattributed()))))
         } else {
             None
         };
-        if let Some(ref r) = primes_res {
-            println!("{}", r);
-        } else {
-            println!("{:<24} │ {:>10} │ {:>10} │ {:>10} │ {:>10} │ {:>8} │ {:>8} │ {:>8}",
-                "primes crate (iter)", "—", "skipped", "(too slow", "for n>1M)", "—", "—", "—");
-        }
 
         // primal iterator
-        let primal_iter = bench("primal (iterator)", n, iterations, 0, primal_crate_sieve);
-        println!("{}", primal_iter);
+        let primal_iter = primal_estimates.remove(&n).unwrap_or_else(||
+            trim(args.retry.run(|| bench("primal (iterator)", n, iterations, args.common.warmup, primal_crate_sieve).unattributed())));
 
         // primal direct sieve
-        let primal_sieve_mem = n as usize / 8;
-        let primal_direct = bench("primal (Sieve::new)", n, iterations, primal_sieve_mem, primal_crate_sieve_direct);
-        println!("{}", primal_direct);
+        let primal_direct = trim(args.retry.run(|| bench("primal (Sieve::new)", n, iterations, args.common.warmup, primal_crate_sieve_direct)));
+
+        // The table is planned from every row at once so each time column
+        // shares a single unit.
+        let ran: Vec<&BenchResult> = [Some(&wofl_flat), Some(&wofl_seg), Some(&wofl_pi), Some(&wofl_atomic), primes_res.as_ref(), Some(&primal_iter), Some(&primal_direct)]
+            .into_iter()
+            .flatten()
+            .collect();
+        let plan = TablePlan::new(&ran, args.common.units);
+        write!(out, "{}", plan.header())?;
+        write!(out, "{}", plan.row(&wofl_flat))?;
+        write!(out, "{}", plan.row(&wofl_seg))?;
+        write!(out, "{}", plan.row(&wofl_pi))?;
+        write!(out, "{}", plan.row(&wofl_atomic))?;
+        match primes_res {
+            Some(ref r) => write!(out, "{}", plan.row(r))?,
+            None => match measured_fit(&primes_history) {
+                Some(fit) => write!(out, "{}", plan.estimated_row("primes crate (iter)", fit.predict(n),
+                    &format!("~ extrapolated, not run (fit t ∝ n^{:.2} over {} sizes)", fit.exponent, fit.points)))?,
+                None => write!(out, "{}", plan.skipped_row("primes crate (iter)", ("(too slow", "for n>1M)")))?,
+            },
+        }
+        write!(out, "{}", plan.row(&primal_iter))?;
+        write!(out, "{}", plan.row(&primal_direct))?;
+        if ran.iter().any(|r| r.name.ends_with("(est.)")) {
+            writeln!(out, "(est.) = split time from a single run to that crate's largest n, not measured at this n")?;
+        }
+
+        if args.common.warmup > 0 {
+            writeln!(out)?;
+            for r in &ran {
+                if let Some(line) = plan.first_call_row(r) {
+                    write!(out, "{}", line)?;
+                }
+            }
+        }
 
-        // Verify all implementations agree
-        assert_eq!(wofl_flat.prime_count, wofl_seg.prime_count,
-            "MISMATCH at n={}: flat={} vs segmented={}", n, wofl_flat.prime_count, wofl_seg.prime_count);
         if let Some(ref r) = primes_res {
-            assert_eq!(wofl_flat.prime_count, r.prime_count,
-                "MISMATCH at n={}: wofl={} vs primes={}", n, wofl_flat.prime_count, r.prime_count);
+            primes_history.push((n, r.median(), !r.name.ends_with("(est.)")));
+        }
+
+        // Verify all implementations agree with the flat sieve. This runs
+        // only after every timed sample for this n is in.
+        for r in &ran[1..] {
+            // prime_pi keeps no output to hash or compare, only its count
+            let level = if std::ptr::eq(*r, &wofl_pi) { args.validate.min(Level::Counts) } else { args.validate };
+            if let Err(mismatch) = validate::check(level, &wofl_flat, r) {
+                panic!("{}", mismatch);
+            }
         }
-        assert_eq!(wofl_flat.prime_count, primal_iter.prime_count,
-            "MISMATCH at n={}: wofl={} vs primal_iter={}", n, wofl_flat.prime_count, primal_iter.prime_count);
-        assert_eq!(wofl_flat.prime_count, primal_direct.prime_count,
-            "MISMATCH at n={}: wofl={} vs primal_direct={}", n, wofl_flat.prime_count, primal_direct.prime_count);
 
         // Summary — find fastest
-        let mut all: Vec<(&str, Duration)> = vec![
+        let variants: Vec<(&str, Duration)> = vec![
             ("wofl flat", wofl_flat.median()),
             ("wofl segmented", wofl_seg.median()),
-            ("primal iter", primal_iter.median()),
-            ("primal sieve", primal_direct.median()),
+            ("wofl prime_pi", wofl_pi.median()),
+            ("wofl atomic", wofl_atomic.median()),
         ];
+        let mut medians = variants.clone();
+        medians.push(("primal iter", primal_iter.median()));
+        medians.push(("primal sieve", primal_direct.median()));
         if let Some(ref r) = primes_res {
-            all.push(("primes crate", r.median()));
-        }
-        let fastest = all.iter().map(|(_, d)| *d).min().unwrap();
-
-        println!("│");
-        println!("│  π({}) = {}   │  All implementations agree ✓",
-            format_with_commas(n), format_with_commas(wofl_flat.prime_count as u64));
-
-        for (name, time) in &all {
-            let ratio = time.as_nanos() as f64 / fastest.as_nanos() as f64;
-            if ratio <= 1.01 {
-                println!("│  {:16} : fastest 🏆", name);
-            } else {
-                println!("│  {:16} : {:.2}x slower", name, ratio);
-            }
-        }
-
-        // Segmented vs flat speedup
-        let seg_speedup = wofl_flat.median().as_nanos() as f64 / wofl_seg.median().as_nanos() as f64;
-        if seg_speedup > 1.05 {
-            println!("│  ⚡ segmented is {:.1}x faster than flat", seg_speedup);
-        } else if seg_speedup < 0.95 {
-            println!("│  ℹ️  flat is {:.1}x faster than segmented (overhead dominates at small n)", 1.0/seg_speedup);
-        } else {
-            println!("│  ≈ segmented ≈ flat (within noise)");
+            medians.push(("primes crate", r.median()));
         }
 
-        println!("│");
-        println!("└──────────────────────────────────────────────────────────────────────────────────");
-        println!();
+        let summary = Summary {
+            n,
+            prime_count: wofl_flat.prime_count,
+            medians,
+            variants,
+            digits: args.common.digits,
+        };
+        write!(out, "{}", render_summary(&summary))?;
     }
 
     // Memory comparison
-    println!("📊 Memory Efficiency @ n=50,000,000");
-    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
     let n = 50_000_000u64;
-    let flat_sieve_bytes = ((n / 2 / 64 + 1) * 8) as usize;
-    let seg_sieve_bytes = SEGMENT_BYTES;
-    let result = wofl_sieve(n);
-    let result_bytes = result.capacity() * 8;
-    println!("  flat sieve array:     {:>10}  (entire range in memory)", format_bytes(flat_sieve_bytes));
-    println!("  segmented buffer:     {:>10}  (single L1 segment, reused) 🏆", format_bytes(seg_sieve_bytes));
-    println!("  result vec (shared):  {:>10}", format_bytes(result_bytes));
-    println!("  flat total:           {:>10}", format_bytes(flat_sieve_bytes + result_bytes));
-    println!("  segmented total:      {:>10}", format_bytes(seg_sieve_bytes + result_bytes));
-    println!("  sieve memory saving:  {:>10.0}x", flat_sieve_bytes as f64 / seg_sieve_bytes as f64);
-    println!("  naive bool array:     {:>10}  (comparison)", format_bytes(n as usize));
-    println!();
-    println!("✓ Benchmark complete!");
+    let (flat_sieve_bytes, result_bytes) = alloc::measure_sieve_bytes(n, sieve_primes);
+    let (seg_sieve_bytes, _) = alloc::measure_sieve_bytes(n, segmented_sieve);
+    write!(out, "{}", render_memory_report(n, flat_sieve_bytes, seg_sieve_bytes, result_bytes, args.common.digits))?;
+    writeln!(out)?;
+    writeln!(out, "✓ Benchmark complete!")?;
+    Ok(())
 }
 
-fn format_with_commas(n: u64) -> String {
-    let s = n.to_string();
-    let mut result = String::new();
-    for (i, c) in s.chars().rev().enumerate() {
-        if i > 0 && i % 3 == 0 {
-            result.push(',');
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use primer_core::half::half_index_bound;
+    use primer_core::segment::segment_words;
+
+    fn summary(medians: Vec<(&str, u64)>) -> Summary<'_> {
+        let medians: Vec<(&str, Duration)> = medians.into_iter()
+            .map(|(name, us)| (name, Duration::from_micros(us)))
+            .collect();
+        Summary {
+            n: 1_000_000,
+            prime_count: 78_498,
+            variants: medians.iter().copied().filter(|(name, _)| name.starts_with("wofl")).collect(),
+            medians,
+            digits: Grouping::default(),
+        }
+    }
+
+    #[test]
+    fn test_measured_fit_ignores_estimates() {
+        let ms = Duration::from_millis;
+        // One measured size and one estimate: not enough to extrapolate
+        assert!(measured_fit(&[(1_000, ms(1), true), (10_000, ms(50), false)]).is_none());
+        // The estimate's 50x would bend the fit; only the measured 10x counts
+        let fit = measured_fit(&[(1_000, ms(1), true), (10_000, ms(50), false), (100_000, ms(100), true)]).unwrap();
+        assert_eq!(fit.points, 2);
+        assert!((fit.exponent - 1.0).abs() < 1e-9, "exponent {}", fit.exponent);
+        assert!(measured_fit(&[]).is_none());
+    }
+
+    #[test]
+    fn test_measured_sieve_bytes_match_analytic() {
+        for n in [1_000_000, 10_000_000] {
+            // The flat bitmap, plus the few scratch words the seeds need
+            let (flat, _) = alloc::measure_sieve_bytes(n, sieve_primes);
+            let bitmap = ((half_index_bound(n) / 64 + 1) * 8) as usize;
+            assert!((bitmap..bitmap + 4096).contains(&flat), "n={} flat={} bitmap={}", n, flat, bitmap);
+
+            // One segment buffer, plus the seed primes up to √n
+            let (seg, _) = alloc::measure_sieve_bytes(n, segmented_sieve);
+            let buffer = segment_words(half_index_bound(n)) * 8;
+            assert!((buffer..buffer + 16 * 1024).contains(&seg), "n={} seg={} buffer={}", n, seg, buffer);
+
+            let (atomic, _) = alloc::measure_sieve_bytes(n, |n| wofl_flat_atomic_sieve(n, 2));
+            assert!((bitmap..bitmap + 16 * 1024).contains(&atomic), "n={} atomic={}", n, atomic);
         }
-        result.push(c);
     }
-    result.chars().rev().collect()
+
+    #[test]
+    fn test_bench_measures_sieve_bytes() {
+        let r = bench("wofl (segmented)", 1_000_000, 2, 0, segmented_sieve);
+        let buffer = segment_words(500_000) * 8;
+        assert!(r.sieve_bytes.is_some_and(|b| (buffer..buffer + 16 * 1024).contains(&b)), "{:?}", r.sieve_bytes);
+        assert_eq!(r.unattributed().sieve_bytes, None);
+    }
+
+    fn args(list: &[&str]) -> Result<Args, String> {
+        parse_args(list.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn test_parse_validate_flag() {
+        assert_eq!(args(&[]).unwrap().validate, Level::Counts);
+        assert_eq!(args(&["--validate", "full"]).unwrap().validate, Level::Full);
+        assert_eq!(args(&["--units", "us", "--validate", "off"]).unwrap().validate, Level::Off);
+        assert!(args(&["--validate"]).is_err());
+        assert!(args(&["--validate", "some"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_reuse_comparisons() {
+        assert!(!args(&[]).unwrap().reuse_comparisons);
+        assert!(args(&["--reuse-comparisons", "--units", "ms"]).unwrap().reuse_comparisons);
+    }
+
+    #[test]
+    fn test_parse_energy() {
+        assert!(!args(&[]).unwrap().energy);
+        assert!(args(&["--energy"]).unwrap().energy);
+    }
+
+    #[test]
+    fn test_comparison_splits_match_measured() {
+        let thresholds = [1_000, 10_000, 65_537];
+        for splits in [primes_crate_splits, primal_crate_splits] {
+            let (primes, split) = splits(&thresholds);
+            assert_eq!(primes, sieve_primes(65_537));
+            let counts: Vec<usize> = split.iter().map(|s| s.count).collect();
+            assert_eq!(counts, vec![168, 1_229, 6_543]);
+        }
+    }
+
+    #[test]
+    fn test_parse_retry_flags() {
+        assert_eq!(args(&[]).unwrap().retry, RetryPolicy::default());
+        let retry = args(&["--noise-threshold", "12.5", "--max-reruns", "0"]).unwrap().retry;
+        assert_eq!(retry, RetryPolicy { threshold: 0.125, max_reruns: 0 });
+        assert_eq!(args(&["--noise-threshold", "8%"]).unwrap().retry.threshold, 0.08);
+        assert!(args(&["--noise-threshold", "-1"]).is_err());
+        assert!(args(&["--noise-threshold", "lots"]).is_err());
+        assert!(args(&["--max-reruns"]).is_err());
+    }
+
+    #[test]
+    fn test_section_open_honours_separator() {
+        let dots = "dot".parse().unwrap();
+        assert!(render_section_open(10_000_000, 25, dots).starts_with("┌─ n = 10.000.000 (25"));
+    }
+
+    #[test]
+    fn test_section_open_snapshot() {
+        assert_eq!(render_section_open(10_000_000, 25, Grouping::default()), concat!(
+            "┌─ n = 10,000,000 (25 iterations) ─────────────────────────────────────────────────────\n",
+            "│\n",
+        ));
+    }
+
+    #[test]
+    fn test_summary_segmented_faster() {
+        let s = summary(vec![("wofl flat", 3_000), ("wofl segmented", 1_000), ("primal sieve", 1_500)]);
+        assert_eq!(render_summary(&s), concat!(
+            "│\n",
+            "│  π(1,000,000) = 78,498   │  All implementations agree ✓\n",
+            "│  wofl flat        : 3.00x slower\n",
+            "│  wofl segmented   : fastest 🏆\n",
+            "│  primal sieve     : 1.50x slower\n",
+            "│\n",
+            "│  Speedup (row vs column):\n",
+            "│                    wofl flat  wofl segmented\n",
+            "│    wofl flat               —           0.33x\n",
+            "│    wofl segmented      3.00x               —\n",
+            "│\n",
+            "└──────────────────────────────────────────────────────────────────────────────────\n",
+            "\n",
+        ));
+    }
+
+    #[test]
+    fn test_summary_flat_faster() {
+        let s = summary(vec![("wofl flat", 1_000), ("wofl segmented", 2_000)]);
+        let out = render_summary(&s);
+        assert!(out.contains("│  wofl flat        : fastest 🏆\n"));
+        assert!(out.contains("│    wofl flat               —           2.00x\n"));
+    }
+
+    #[test]
+    fn test_summary_fastest_marker_tolerates_one_percent() {
+        // Anything within 1% of the fastest median is also marked fastest.
+        let s = summary(vec![("wofl flat", 1_000), ("wofl segmented", 1_005), ("primal iter", 2_500)]);
+        assert_eq!(render_summary(&s), concat!(
+            "│\n",
+            "│  π(1,000,000) = 78,498   │  All implementations agree ✓\n",
+            "│  wofl flat        : fastest 🏆\n",
+            "│  wofl segmented   : fastest 🏆\n",
+            "│  primal iter      : 2.50x slower\n",
+            "│\n",
+            "│  Speedup (row vs column):\n",
+            "│                    wofl flat  wofl segmented\n",
+            "│    wofl flat               —           1.00x\n",
+            "│    wofl segmented      1.00x               —\n",
+            "│\n",
+            "└──────────────────────────────────────────────────────────────────────────────────\n",
+            "\n",
+        ));
+    }
+
+    #[test]
+    fn test_summary_zero_median() {
+        let s = summary(vec![("wofl flat", 0), ("wofl segmented", 0), ("primal sieve", 40)]);
+        let out = render_summary(&s);
+        assert!(out.contains("│  wofl flat        : —\n│  wofl segmented   : —\n│  primal sieve     : —\n"), "{}", out);
+        assert!(out.contains("│    wofl flat               —               —\n"), "{}", out);
+    }
+
+    #[test]
+    fn test_summary_single_variant_has_no_matrix() {
+        let s = summary(vec![("wofl flat", 1_000), ("primal iter", 2_500)]);
+        assert!(!render_summary(&s).contains("Speedup"));
+    }
+
+    #[test]
+    fn test_memory_report_snapshot() {
+        assert_eq!(render_memory_report(50_000_000, 3_125_008, 32 * 1024, 24_432_128, Grouping::default()), concat!(
+            "📊 Memory Efficiency @ n=50,000,000 (measured peaks)\n",
+            "━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━\n",
+            "  flat sieve array:        3.0 MiB  (entire range in memory)\n",
+            "  segmented buffer:       32.0 KiB  (one L1 segment + seed primes) 🏆\n",
+            "  result vec (shared):    23.3 MiB\n",
+            "  flat total:             26.3 MiB\n",
+            "  segmented total:        23.3 MiB\n",
+            "  sieve memory saving:          95x\n",
+        ));
+    }
 }
@@ -0,0 +1,146 @@
+//! A counting global allocator, so memory columns are measured rather than
+//! worked out by hand.
+//!
+//! A binary opts in with
+//!
+//! ```ignore
+//! #[global_allocator]
+//! static ALLOC: benchkit::alloc::CountingAlloc = benchkit::alloc::CountingAlloc;
+//! ```
+//!
+//! Counts are kept per thread: `measure_peak` sees what the calling thread
+//! allocates, unaffected by tests or workers running alongside it. Memory
+//! a spawned worker allocates for itself is not included.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static LIVE: Cell<isize> = const { Cell::new(0) };
+    static PEAK: Cell<isize> = const { Cell::new(0) };
+}
+
+/// `System`, plus per-thread live and peak byte counts.
+pub struct CountingAlloc;
+
+fn record(delta: isize) {
+    // `try_with`: allocations can still happen while the thread-locals are
+    // being torn down at thread exit.
+    let _ = LIVE.try_with(|live| {
+        let now = live.get() + delta;
+        live.set(now);
+        let _ = PEAK.try_with(|peak| peak.set(peak.get().max(now)));
+    });
+}
+
+fn record_alloc(p: *mut u8, size: usize) -> *mut u8 {
+    if !p.is_null() {
+        if !INSTALLED.load(Ordering::Relaxed) {
+            INSTALLED.store(true, Ordering::Relaxed);
+        }
+        record(size as isize);
+    }
+    p
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record_alloc(System.alloc(layout), layout.size())
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        record(-(layout.size() as isize));
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        record_alloc(System.alloc_zeroed(layout), layout.size())
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let p = System.realloc(ptr, layout, new_size);
+        if !p.is_null() {
+            record(new_size as isize - layout.size() as isize);
+        }
+        p
+    }
+}
+
+/// Whether this process runs on `CountingAlloc`.
+pub fn is_installed() -> bool {
+    INSTALLED.load(Ordering::Relaxed)
+}
+
+/// Run `f` and report the most memory the calling thread held at once
+/// while it ran, above what it held on entry. `None` when `CountingAlloc`
+/// is not the global allocator.
+pub fn measure_peak<T, F: FnOnce() -> T>(f: F) -> (T, Option<usize>) {
+    let base = LIVE.with(Cell::get);
+    let outer_peak = PEAK.with(|peak| peak.replace(base));
+    let value = f();
+    let peak = PEAK.with(|peak| peak.replace(outer_peak.max(peak.get())));
+    let bytes = is_installed().then(|| (peak - base).max(0) as usize);
+    (value, bytes)
+}
+
+/// Peak working memory of one `f(n)` call beyond the `Vec` it returns,
+/// and that result's own size, both in bytes. Panics when `CountingAlloc`
+/// is not the global allocator.
+pub fn measure_sieve_bytes(n: u64, f: impl FnOnce(u64) -> Vec<u64>) -> (usize, usize) {
+    let (result, peak) = measure_peak(|| f(n));
+    let result_bytes = result.capacity() * std::mem::size_of::<u64>();
+    let peak = peak.expect("measure_sieve_bytes: CountingAlloc is not the global allocator");
+    (peak.saturating_sub(result_bytes), result_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[global_allocator]
+    static ALLOC: CountingAlloc = CountingAlloc;
+
+    #[test]
+    fn test_peak_counts_live_allocation() {
+        let (v, peak) = measure_peak(|| vec![0u8; 1 << 20]);
+        assert!(is_installed());
+        let peak = peak.unwrap();
+        assert!((1 << 20..(1 << 20) + 4096).contains(&peak), "peak {}", peak);
+        drop(v);
+    }
+
+    #[test]
+    fn test_peak_includes_freed_scratch() {
+        let ((), peak) = measure_peak(|| {
+            let scratch = vec![0u64; 10_000];
+            std::hint::black_box(&scratch);
+        });
+        assert!(peak.unwrap() >= 80_000);
+    }
+
+    #[test]
+    fn test_nested_measurements() {
+        let (inner, outer) = measure_peak(|| {
+            let big = vec![0u8; 200_000];
+            std::hint::black_box(&big);
+            drop(big);
+            measure_peak(|| vec![0u8; 50_000]).1.unwrap()
+        });
+        assert!((50_000..60_000).contains(&inner), "inner {}", inner);
+        assert!(outer.unwrap() >= 200_000, "the inner reset must not hide the outer peak");
+    }
+
+    #[test]
+    fn test_sieve_bytes_split_scratch_from_result() {
+        let (sieve, result) = measure_sieve_bytes(1_000, |n| {
+            let scratch = vec![0u64; n as usize];
+            std::hint::black_box(&scratch);
+            Vec::with_capacity(10)
+        });
+        assert_eq!(result, 80);
+        assert!((8_000..8_000 + 4096).contains(&sieve), "sieve {}", sieve);
+    }
+}
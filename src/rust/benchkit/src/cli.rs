@@ -0,0 +1,151 @@
+//! The command-line flags every bench binary takes.
+//!
+//! `--units`, `--warmup`, `--digit-separator` and `--sizes` mean the same
+//! thing in each binary, so they are parsed once here. A binary keeps its
+//! own argument loop for the flags only it has and hands everything else
+//! to `CommonArgs::parse_flag`:
+//!
+//! ```
+//! use benchkit::cli::CommonArgs;
+//!
+//! let mut common = CommonArgs::new(&[1_000, 10_000]);
+//! let mut args = ["--warmup", "0", "--fast"].iter().map(|s| s.to_string()).peekable();
+//! let mut fast = false;
+//! while let Some(arg) = args.next() {
+//!     if !common.parse_flag(&arg, &mut args)? {
+//!         match arg.as_str() {
+//!             "--fast" => fast = true,
+//!             other => return Err(format!("unknown argument '{}'", other)),
+//!         }
+//!     }
+//! }
+//! assert_eq!((common.warmup, fast), (0, true));
+//! # Ok::<(), String>(())
+//! ```
+
+use std::iter::Peekable;
+
+use crate::{parse_grouped, Grouping, Units};
+
+/// Values of the shared flags, or their defaults.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommonArgs {
+    pub units: Units,
+    /// Untimed calls before each timed run; 0 allowed.
+    pub warmup: usize,
+    pub digits: Grouping,
+    pub sizes: Vec<u64>,
+}
+
+impl CommonArgs {
+    /// The defaults: automatic units, 2 warmup calls, comma grouping and
+    /// the binary's own `default_sizes`.
+    pub fn new(default_sizes: &[u64]) -> CommonArgs {
+        CommonArgs {
+            units: Units::Auto,
+            warmup: 2,
+            digits: Grouping::default(),
+            sizes: default_sizes.to_vec(),
+        }
+    }
+
+    /// Parse `flag` if it is one of the shared ones, taking its values
+    /// from `args`. `Ok(false)` leaves any other flag to the caller.
+    pub fn parse_flag<I>(&mut self, flag: &str, args: &mut Peekable<I>) -> Result<bool, String>
+    where
+        I: Iterator<Item = String>,
+    {
+        match flag {
+            "--units" => {
+                let value = args.next().ok_or("--units needs a value")?;
+                self.units = value.parse()?;
+            }
+            "--warmup" => {
+                let value = args.next().ok_or("--warmup needs a value")?;
+                self.warmup = value.parse()
+                    .map_err(|_| format!("invalid warmup count '{}'", value))?;
+            }
+            "--digit-separator" => {
+                let value = args.next().ok_or("--digit-separator needs a value")?;
+                self.digits = value.parse()?;
+            }
+            "--sizes" => {
+                self.sizes.clear();
+                while let Some(value) = args.next_if(|a| !a.starts_with("--")) {
+                    self.sizes.push(parse_grouped(&value)?);
+                }
+                if self.sizes.is_empty() {
+                    return Err("--sizes needs at least one value".to_string());
+                }
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TimeUnit;
+
+    /// Every argument through `parse_flag`; an unknown one is an error.
+    fn parse(list: &[&str]) -> Result<CommonArgs, String> {
+        let mut common = CommonArgs::new(&[10, 20]);
+        let mut args = list.iter().map(|s| s.to_string()).peekable();
+        while let Some(arg) = args.next() {
+            if !common.parse_flag(&arg, &mut args)? {
+                return Err(format!("unknown argument '{}'", arg));
+            }
+        }
+        Ok(common)
+    }
+
+    #[test]
+    fn test_defaults() {
+        let common = parse(&[]).unwrap();
+        assert_eq!(common, CommonArgs::new(&[10, 20]));
+        assert_eq!((common.units, common.warmup, common.sizes), (Units::Auto, 2, vec![10, 20]));
+        assert_eq!(common.digits, Grouping::default());
+    }
+
+    #[test]
+    fn test_units_flag() {
+        assert_eq!(parse(&["--units", "ms"]).unwrap().units, Units::Fixed(TimeUnit::Ms));
+        assert!(parse(&["--units"]).is_err());
+        assert!(parse(&["--units", "fortnights"]).is_err());
+    }
+
+    #[test]
+    fn test_warmup_flag() {
+        assert_eq!(parse(&["--warmup", "0"]).unwrap().warmup, 0);
+        assert_eq!(parse(&["--warmup", "5"]).unwrap().warmup, 5);
+        assert!(parse(&["--warmup"]).is_err());
+        assert!(parse(&["--warmup", "-1"]).is_err());
+    }
+
+    #[test]
+    fn test_sizes_and_separator() {
+        let a = parse(&["--sizes", "50,000,000", "10_000", "--digit-separator", "none"]).unwrap();
+        assert_eq!(a.sizes, vec![50_000_000, 10_000]);
+        assert_eq!(a.digits.separator, None);
+        let a = parse(&["--digit-separator", "space", "--sizes", "1 000 000", "5,000"]).unwrap();
+        assert_eq!(a.sizes, vec![1_000_000, 5_000]);
+        assert_eq!(a.digits.format(1_000_000), "1 000 000");
+        assert!(parse(&["--sizes"]).is_err());
+        assert!(parse(&["--sizes", "--units", "ms"]).is_err());
+        assert!(parse(&["--sizes", "1e6"]).is_err());
+        assert!(parse(&["--digit-separator", "semicolon"]).is_err());
+    }
+
+    #[test]
+    fn test_other_flags_left_to_the_caller() {
+        let mut common = CommonArgs::new(&[10]);
+        let mut args = ["7".to_string()].into_iter().peekable();
+        assert_eq!(common.parse_flag("--energy", &mut args), Ok(false));
+        assert_eq!(common.parse_flag("--bogus", &mut args), Ok(false));
+        // Nothing was consumed or changed
+        assert_eq!(args.next().as_deref(), Some("7"));
+        assert_eq!(common, CommonArgs::new(&[10]));
+    }
+}
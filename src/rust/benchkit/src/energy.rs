@@ -0,0 +1,198 @@
+//! Package energy per run from Linux RAPL counters.
+//!
+//! Reading needs the `energy` feature, Linux, an Intel/AMD RAPL domain
+//! under /sys/class/powercap and permission to read it (recent kernels
+//! make `energy_uj` root-only). Anything missing makes `enable` fail with
+//! a reason, and the energy columns are simply left out.
+//!
+//! Caveat: the counters are per package, so they include whatever else
+//! the machine is doing. `enable` samples the package at rest first and
+//! every run is charged only for energy above that idle draw, which takes
+//! out steady background load but not bursts.
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Where the first package domain lives.
+#[cfg(feature = "energy")]
+const RAPL_DOMAIN: &str = "/sys/class/powercap/intel-rapl:0";
+
+/// How long `enable` watches the idle package for its baseline.
+#[cfg(feature = "energy")]
+const IDLE_SAMPLE: Duration = Duration::from_millis(250);
+
+static ACTIVE: OnceLock<Meter> = OnceLock::new();
+
+pub struct Meter {
+    /// The domain's `energy_uj` file; `None` for synthetic meters in tests.
+    path: Option<std::path::PathBuf>,
+    /// The counter wraps to 0 after this many microjoules.
+    max_range_uj: u64,
+    /// Idle draw in microwatts, subtracted from every reading pair.
+    idle_uw: u64,
+}
+
+impl Meter {
+    #[cfg(feature = "energy")]
+    fn open() -> Result<Meter, String> {
+        use std::path::Path;
+
+        let domain = Path::new(RAPL_DOMAIN);
+        let read = |file: &str| -> Result<u64, String> {
+            let path = domain.join(file);
+            let text = std::fs::read_to_string(&path).map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => format!("no RAPL domain at {}", domain.display()),
+                std::io::ErrorKind::PermissionDenied => format!("{} is not readable (try as root)", path.display()),
+                _ => format!("{}: {}", path.display(), e),
+            })?;
+            text.trim().parse().map_err(|_| format!("{}: unexpected contents", path.display()))
+        };
+        let max_range_uj = read("max_energy_range_uj")?;
+        let mut meter = Meter { path: Some(domain.join("energy_uj")), max_range_uj, idle_uw: 0 };
+
+        let before = read("energy_uj")?;
+        let start = std::time::Instant::now();
+        std::thread::sleep(IDLE_SAMPLE);
+        let after = read("energy_uj")?;
+        let idle_uj = delta_uj(before, after, max_range_uj)
+            .ok_or_else(|| format!("energy_uj read {} past max_energy_range_uj {}", before, max_range_uj))?;
+        meter.idle_uw = average_microwatts(idle_uj, start.elapsed());
+        Ok(meter)
+    }
+
+    #[cfg(not(feature = "energy"))]
+    fn open() -> Result<Meter, String> {
+        Err("built without the `energy` feature".to_string())
+    }
+
+    /// The raw counter, in microjoules.
+    pub fn read(&self) -> Option<u64> {
+        let text = std::fs::read_to_string(self.path.as_ref()?).ok()?;
+        text.trim().parse().ok()
+    }
+
+    /// Energy of one run between two raw readings, less the idle draw over
+    /// `elapsed`. Never negative; `None` when the readings can't be
+    /// unwrapped (see `delta_uj`).
+    pub fn attribute(&self, before: u64, after: u64, elapsed: Duration) -> Option<u64> {
+        let idle = (self.idle_uw as u128 * elapsed.as_nanos() / 1_000_000_000) as u64;
+        delta_uj(before, after, self.max_range_uj).map(|uj| uj.saturating_sub(idle))
+    }
+
+    /// Idle draw measured at startup, in microwatts.
+    pub fn idle_microwatts(&self) -> u64 {
+        self.idle_uw
+    }
+}
+
+/// Microjoules between two readings of a counter that runs over
+/// 0..=`max_range_uj` and then wraps to 0, so a wrap itself counts one.
+/// Assumes at most one wrap in between, which at package power takes tens
+/// of minutes. `None` when a wrapped `before` lies above the range, as
+/// with a stale or misread range file; the sample is then dropped.
+pub fn delta_uj(before: u64, after: u64, max_range_uj: u64) -> Option<u64> {
+    if after >= before {
+        Some(after - before)
+    } else {
+        // after < before ≤ max_range_uj, so the sum stays ≤ max_range_uj
+        max_range_uj.checked_sub(before).map(|to_top| to_top + after + 1)
+    }
+}
+
+/// Average draw in microwatts of `energy_uj` spent over `elapsed`.
+pub fn average_microwatts(energy_uj: u64, elapsed: Duration) -> u64 {
+    let nanos = elapsed.as_nanos().max(1);
+    (energy_uj as u128 * 1_000_000_000 / nanos) as u64
+}
+
+/// Open the package counter, take the idle baseline and make the meter
+/// visible to `bench`. Calling it again returns the first meter.
+pub fn enable() -> Result<&'static Meter, String> {
+    if let Some(meter) = ACTIVE.get() {
+        return Ok(meter);
+    }
+    let meter = Meter::open()?;
+    Ok(ACTIVE.get_or_init(|| meter))
+}
+
+/// The meter `enable` installed, if any.
+pub fn active() -> Option<&'static Meter> {
+    ACTIVE.get()
+}
+
+/// `1.25 J`, `830.4 mJ`, `95 µJ`.
+pub fn format_microjoules(uj: u64) -> String {
+    if uj < 1_000 {
+        format!("{} µJ", uj)
+    } else if uj < 1_000_000 {
+        format!("{:.1} mJ", uj as f64 / 1e3)
+    } else {
+        format!("{:.2} J", uj as f64 / 1e6)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn synthetic(max_range_uj: u64, idle_uw: u64) -> Meter {
+        Meter { path: None, max_range_uj, idle_uw }
+    }
+
+    #[test]
+    fn test_delta_without_wrap() {
+        assert_eq!(delta_uj(1_000, 1_000, 10_000), Some(0));
+        assert_eq!(delta_uj(1_000, 4_500, 10_000), Some(3_500));
+    }
+
+    #[test]
+    fn test_delta_across_wrap() {
+        // 9,000 → 10,000 → wraps to 0 → 500: 1,000 + 1 + 500
+        assert_eq!(delta_uj(9_000, 500, 10_000), Some(1_501));
+        assert_eq!(delta_uj(9_999, 0, 10_000), Some(2));
+        assert_eq!(delta_uj(10_000, 0, 10_000), Some(1));
+        // The real range on most Intel parts, ~262 kJ
+        let max = 262_143_328_850;
+        assert_eq!(delta_uj(max - 10, 20, max), Some(31));
+        // The largest range can't overflow
+        assert_eq!(delta_uj(u64::MAX, u64::MAX - 1, u64::MAX), Some(u64::MAX));
+    }
+
+    #[test]
+    fn test_delta_before_above_range() {
+        // A stale range file: the counter already reads past it
+        assert_eq!(delta_uj(10_001, 500, 10_000), None);
+        assert_eq!(delta_uj(u64::MAX, 0, 10_000), None);
+        assert_eq!(synthetic(10_000, 0).attribute(20_000, 5, Duration::from_millis(1)), None);
+        // Without a wrap the range doesn't come into it
+        assert_eq!(delta_uj(10_001, 10_500, 10_000), Some(499));
+    }
+
+    #[test]
+    fn test_idle_baseline_subtraction() {
+        // 250 ms at rest used 2.5 J → 10 W idle
+        let idle = average_microwatts(2_500_000, Duration::from_millis(250));
+        assert_eq!(idle, 10_000_000);
+
+        let meter = synthetic(u64::MAX, idle);
+        // A 100 ms run that used 1.5 J is charged 1.5 J - 10 W × 0.1 s
+        assert_eq!(meter.attribute(0, 1_500_000, Duration::from_millis(100)), Some(500_000));
+        // Less than the idle draw clamps to zero rather than going negative
+        assert_eq!(meter.attribute(0, 900_000, Duration::from_millis(100)), Some(0));
+        // Subtraction applies after unwrapping
+        let wrapping = synthetic(10_000_000, idle);
+        assert_eq!(wrapping.attribute(9_500_000, 1_000_000, Duration::from_millis(100)), Some(500_001));
+    }
+
+    #[test]
+    fn test_synthetic_meter_has_no_counter() {
+        assert_eq!(synthetic(1, 0).read(), None);
+    }
+
+    #[test]
+    fn test_format_microjoules() {
+        assert_eq!(format_microjoules(95), "95 µJ");
+        assert_eq!(format_microjoules(830_400), "830.4 mJ");
+        assert_eq!(format_microjoules(1_250_000), "1.25 J");
+    }
+}
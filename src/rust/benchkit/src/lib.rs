@@ -0,0 +1,412 @@
+//! Shared benchmarking machinery for the primer bench binaries.
+//!
+//! Timing (`bench`), memory and energy measurement (`alloc`, `energy`),
+//! per-run statistics (`BenchResult`), table rendering (`report`), re-runs
+//! of noisy rows (`stability`), split-time estimates (`splits`),
+//! extrapolation to skipped sizes (`scaling`), post-run checks
+//! (`validate`), unit formatting and parsing (`units`), the flags every
+//! binary shares (`cli`) and the L1 segment description for report
+//! headers (`segment`) live here so the binaries only decide *what* to
+//! run.
+
+use std::cell::Cell;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+pub mod alloc;
+pub mod cli;
+pub mod energy;
+pub mod report;
+pub mod scaling;
+pub mod segment;
+pub mod splits;
+pub mod stability;
+pub mod units;
+pub mod validate;
+
+pub use report::{SpeedupMatrix, TablePlan, Units};
+pub use units::{Bytes, Elapsed, Percent, Ratio, TimeUnit};
+pub use stability::RetryPolicy;
+
+// ─── Results ───────────────────────────────────────────────────────────────
+
+pub struct BenchResult {
+    pub name: String,
+    pub n: u64,
+    pub prime_count: usize,
+    pub times: Vec<Duration>,
+    /// Peak working memory of one call beyond its result, as measured by
+    /// `alloc::CountingAlloc`. `None` when nothing was measured or the
+    /// figure can't be attributed to the sieve.
+    pub sieve_bytes: Option<usize>,
+    pub result_bytes: usize,
+    /// Output of the last timed iteration, kept for `validate`. Empty after
+    /// `without_output`.
+    pub output: Vec<u64>,
+    /// Warmup iterations in call order. Never part of the statistics below.
+    pub warmup_times: Vec<Duration>,
+    /// Package energy of each timed iteration above idle, in microjoules.
+    /// Empty unless `energy::enable` succeeded.
+    pub energy_uj: Vec<u64>,
+    /// Extra runs `RetryPolicy` made before keeping this sample set.
+    pub reruns: usize,
+    /// Still above the noise threshold after all re-runs.
+    pub noisy: bool,
+}
+
+impl BenchResult {
+    /// Drop the measured sieve memory, for implementations whose working
+    /// memory can't be told apart from their output (a lazy iterator grows
+    /// both together).
+    pub fn unattributed(self) -> BenchResult {
+        BenchResult { sieve_bytes: None, ..self }
+    }
+
+    /// Free the output, for results that are only ever checked by count.
+    /// A size's rows otherwise hold every implementation's primes until the
+    /// checks run.
+    pub fn without_output(self) -> BenchResult {
+        BenchResult { output: Vec::new(), ..self }
+    }
+
+    /// The very first call, if any warmup ran — page faults, cold caches and
+    /// frequency ramp-up included.
+    pub fn first_call(&self) -> Option<Duration> {
+        self.warmup_times.first().copied()
+    }
+
+    pub fn median(&self) -> Duration {
+        let mut sorted: Vec<Duration> = self.times.clone();
+        sorted.sort();
+        sorted[sorted.len() / 2]
+    }
+
+    pub fn min(&self) -> Duration {
+        *self.times.iter().min().unwrap()
+    }
+
+    pub fn max(&self) -> Duration {
+        *self.times.iter().max().unwrap()
+    }
+
+    pub fn mean(&self) -> Duration {
+        let total: Duration = self.times.iter().sum();
+        total / self.times.len() as u32
+    }
+
+    /// Population standard deviation, in nanoseconds.
+    pub fn stddev_ns(&self) -> f64 {
+        let mean = self.mean().as_nanos() as f64;
+        let variance = self.times.iter()
+            .map(|t| {
+                let diff = t.as_nanos() as f64 - mean;
+                diff * diff
+            })
+            .sum::<f64>() / self.times.len() as f64;
+        variance.sqrt()
+    }
+
+    pub fn median_energy_uj(&self) -> Option<u64> {
+        let mut sorted = self.energy_uj.clone();
+        sorted.sort();
+        sorted.get(sorted.len() / 2).copied()
+    }
+
+    /// Primes found per joule at the median energy.
+    pub fn primes_per_joule(&self) -> Option<f64> {
+        self.median_energy_uj()
+            .filter(|&uj| uj > 0)
+            .map(|uj| self.prime_count as f64 / (uj as f64 / 1e6))
+    }
+
+    /// Median absolute deviation over the median: 0 for perfectly steady
+    /// samples, and unlike σ / mean not dragged up by one outlier.
+    pub fn stability(&self) -> f64 {
+        let median = self.median().as_nanos() as f64;
+        let mut deviations: Vec<f64> = self.times.iter()
+            .map(|t| (t.as_nanos() as f64 - median).abs())
+            .collect();
+        deviations.sort_by(f64::total_cmp);
+        deviations[deviations.len() / 2] / median
+    }
+}
+
+/// A result named `name` with the given timed samples and every other
+/// field empty. Test fixtures start here and override what they need with
+/// struct-update syntax, so a new field only has to be added once.
+#[cfg(test)]
+pub(crate) fn test_result(name: &str, times_ns: &[u64]) -> BenchResult {
+    BenchResult {
+        name: name.to_string(),
+        n: 0,
+        prime_count: 0,
+        times: times_ns.iter().map(|&t| Duration::from_nanos(t)).collect(),
+        sieve_bytes: None,
+        result_bytes: 0,
+        output: Vec::new(),
+        warmup_times: Vec::new(),
+        energy_uj: Vec::new(),
+        reruns: 0,
+        noisy: false,
+    }
+}
+
+// ─── Timing ────────────────────────────────────────────────────────────────
+
+/// Time `warmup` untimed-for-statistics calls, then `iterations` measured
+/// ones. Warmup durations are kept separately in `warmup_times`.
+///
+/// `sieve_bytes` is the largest peak any timed call reached above its own
+/// result, when the binary runs on `alloc::CountingAlloc`. With an
+/// `energy` meter enabled, each timed call's energy is read around it.
+pub fn bench<F>(name: &str, n: u64, iterations: usize, warmup: usize, f: F) -> BenchResult
+where
+    F: Fn(u64) -> Vec<u64>,
+{
+    let mut warmup_times = Vec::with_capacity(warmup);
+    for _ in 0..warmup {
+        let start = Instant::now();
+        let result = f(n);
+        warmup_times.push(start.elapsed());
+        std::hint::black_box(result);
+    }
+
+    let mut times = Vec::with_capacity(iterations);
+    let mut prime_count = 0;
+    let mut result_bytes = 0;
+    let mut sieve_bytes = None;
+    let mut energy_uj = Vec::new();
+    let meter = energy::active();
+    let mut output = Vec::new();
+
+    for _ in 0..iterations {
+        // Drop the previous output first so it isn't counted in this peak
+        drop(std::mem::take(&mut output));
+        let before = meter.and_then(|m| m.read());
+        let ((result, elapsed), peak) = alloc::measure_peak(|| {
+            let start = Instant::now();
+            let result = f(n);
+            (result, start.elapsed())
+        });
+        if let (Some(m), Some(before)) = (meter, before) {
+            // A pair that can't be unwrapped is skipped, not charged
+            if let Some(uj) = m.read().and_then(|after| m.attribute(before, after, elapsed)) {
+                energy_uj.push(uj);
+            }
+        }
+        prime_count = result.len();
+        result_bytes = result.capacity() * std::mem::size_of::<u64>();
+        if let Some(peak) = peak {
+            sieve_bytes = sieve_bytes.max(Some(peak.saturating_sub(result_bytes)));
+        }
+        times.push(elapsed);
+        output = std::hint::black_box(result);
+    }
+
+    BenchResult {
+        name: name.to_string(),
+        n,
+        prime_count,
+        times,
+        sieve_bytes,
+        result_bytes,
+        output,
+        warmup_times,
+        energy_uj,
+        reruns: 0,
+        noisy: false,
+    }
+}
+
+/// `bench` for implementations that only count: `f` returns π(n) rather
+/// than the primes, so `output` stays empty, `result_bytes` is 0 and all
+/// of the measured peak is sieve memory. Validate these rows at
+/// `Level::Counts` at most.
+pub fn bench_count<F>(name: &str, n: u64, iterations: usize, warmup: usize, f: F) -> BenchResult
+where
+    F: Fn(u64) -> u64,
+{
+    // An empty Vec never allocates, so the count travels on the side
+    let count = Cell::new(0);
+    let result = bench(name, n, iterations, warmup, |n| {
+        count.set(f(n));
+        Vec::new()
+    });
+    BenchResult { prime_count: count.get() as usize, ..result }
+}
+
+// ─── Formatting helpers ────────────────────────────────────────────────────
+
+/// `n` with `separator` between every `group` digits, counted from the
+/// right: `group_digits(1234567, ',', 3)` is `"1,234,567"`.
+pub fn group_digits(n: u64, separator: char, group: usize) -> String {
+    assert!(group > 0, "group_digits: group size must be at least 1");
+    let s = n.to_string();
+    let mut result = String::new();
+    for (i, c) in s.chars().rev().enumerate() {
+        if i > 0 && i % group == 0 {
+            result.push(separator);
+        }
+        result.push(c);
+    }
+    result.chars().rev().collect()
+}
+
+/// Separators `parse_grouped` skips: anything `Grouping` can print.
+const GROUP_SEPARATORS: [char; 6] = [',', '.', '_', ' ', '\'', '\u{2009}'];
+
+/// Parse an integer that may carry digit-group separators, so that
+/// `50,000,000`, `50_000_000` and `50 000 000` all read as 50000000.
+pub fn parse_grouped(s: &str) -> Result<u64, String> {
+    let digits: String = s.chars().filter(|c| !GROUP_SEPARATORS.contains(c)).collect();
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("invalid number '{}'", s));
+    }
+    digits.parse().map_err(|_| format!("number '{}' does not fit in u64", s))
+}
+
+/// How the binaries print large integers, chosen with `--digit-separator`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Grouping {
+    /// `None` prints plain digits.
+    pub separator: Option<char>,
+    pub group: usize,
+}
+
+impl Default for Grouping {
+    fn default() -> Grouping {
+        Grouping { separator: Some(','), group: 3 }
+    }
+}
+
+impl Grouping {
+    pub fn format(&self, n: u64) -> String {
+        match self.separator {
+            Some(c) => group_digits(n, c, self.group),
+            None => n.to_string(),
+        }
+    }
+}
+
+impl FromStr for Grouping {
+    type Err = String;
+
+    /// A separator name (`comma`, `dot`, `space`, `thin`, `underscore`,
+    /// `apostrophe`, `none`) or one of those characters itself.
+    fn from_str(s: &str) -> Result<Grouping, String> {
+        let separator = match s {
+            "none" => None,
+            "comma" | "," => Some(','),
+            "dot" | "." => Some('.'),
+            "space" | " " => Some(' '),
+            "thin" | "\u{2009}" => Some('\u{2009}'),
+            "underscore" | "_" => Some('_'),
+            "apostrophe" | "'" => Some('\''),
+            other => return Err(format!(
+                "unknown digit separator '{}' (expected comma, dot, space, thin, underscore, apostrophe or none)",
+                other)),
+        };
+        Ok(Grouping { separator, ..Grouping::default() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_digits() {
+        assert_eq!(group_digits(0, ',', 3), "0");
+        assert_eq!(group_digits(1, ',', 3), "1");
+        assert_eq!(group_digits(999, ',', 3), "999");
+        assert_eq!(group_digits(1000, ',', 3), "1,000");
+        assert_eq!(group_digits(u64::MAX, ',', 3), "18,446,744,073,709,551,615");
+        assert_eq!(group_digits(u64::MAX, '.', 4), "1844.6744.0737.0955.1615");
+        assert_eq!(group_digits(1234567, '_', 5), "12_34567");
+        assert_eq!(group_digits(123, ' ', 1), "1 2 3");
+    }
+
+    #[test]
+    fn test_parse_grouped_round_trip() {
+        for n in [0, 1, 999, 1000, 50_000_000, u64::MAX] {
+            for sep in [',', '.', '_', ' ', '\'', '\u{2009}'] {
+                for group in [1, 2, 3, 4, 7] {
+                    assert_eq!(parse_grouped(&group_digits(n, sep, group)), Ok(n), "{} {:?} {}", n, sep, group);
+                }
+            }
+        }
+        assert!(parse_grouped("").is_err());
+        assert!(parse_grouped(",").is_err());
+        assert!(parse_grouped("12a").is_err());
+        assert!(parse_grouped("-5").is_err());
+        assert!(parse_grouped("18,446,744,073,709,551,616").is_err());
+    }
+
+    #[test]
+    fn test_grouping_parse() {
+        assert_eq!("none".parse::<Grouping>().unwrap().format(1_000_000), "1000000");
+        assert_eq!("dot".parse::<Grouping>().unwrap().format(1_000_000), "1.000.000");
+        assert_eq!("_".parse::<Grouping>().unwrap().format(1_000_000), "1_000_000");
+        assert_eq!("thin".parse::<Grouping>().unwrap().format(1_000), "1\u{2009}000");
+        assert_eq!(Grouping::default().format(1_000_000), "1,000,000");
+        assert!("semicolon".parse::<Grouping>().is_err());
+    }
+
+    #[test]
+    fn test_stddev_ns() {
+        let r = test_result("", &[800, 900, 1_000]);
+        assert!((r.stddev_ns() - 81.649_658).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_stability_is_mad_over_median() {
+        let r = |times: &[u64]| test_result("", times);
+        assert_eq!(r(&[500, 500, 500]).stability(), 0.0);
+        // Deviations from 1000: 0, 100, 100, 200, 5000 → MAD 100.
+        assert_eq!(r(&[1_000, 900, 1_100, 800, 6_000]).stability(), 0.1);
+    }
+
+    #[test]
+    fn test_warmup_kept_out_of_statistics() {
+        // First call sleeps; every later call returns at once.
+        let calls = Cell::new(0);
+        let slow_first = |n: u64| {
+            if calls.get() == 0 {
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            calls.set(calls.get() + 1);
+            vec![n]
+        };
+
+        let r = bench("slow first", 7, 5, 2, slow_first);
+        assert_eq!(calls.get(), 7);
+        assert_eq!(r.warmup_times.len(), 2);
+        assert_eq!(r.times.len(), 5);
+        assert!(r.first_call().unwrap() >= Duration::from_millis(50));
+        assert!(r.max() < Duration::from_millis(50));
+        assert_eq!(r.output, vec![7]);
+
+        // Without warmup the slow call is the first timed sample.
+        calls.set(0);
+        let r = bench("slow first", 7, 5, 0, slow_first);
+        assert_eq!(r.first_call(), None);
+        assert!(r.max() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_bench_count_keeps_no_output() {
+        let r = bench_count("count", 100, 3, 1, |n| n / 4);
+        assert_eq!(r.prime_count, 25);
+        assert_eq!(r.times.len(), 3);
+        assert_eq!(r.warmup_times.len(), 1);
+        assert!(r.output.is_empty());
+        assert_eq!(r.result_bytes, 0);
+    }
+
+    #[test]
+    fn test_without_output_keeps_the_count() {
+        let r = bench("keep", 7, 3, 0, |n| vec![2, 3, 5, n]).without_output();
+        assert!(r.output.is_empty());
+        assert_eq!((r.prime_count, r.result_bytes, r.times.len()), (4, 32, 3));
+    }
+}
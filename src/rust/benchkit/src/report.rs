@@ -0,0 +1,444 @@
+//! Table planning and rendering.
+//!
+//! A table is planned once from all of its rows: the plan fixes one time
+//! unit for every time column (σ included) and sizes each column to its
+//! widest cell, then renders the header and rows against that plan.
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::units::{Bytes, Percent, Ratio, TimeUnit};
+use crate::{energy, BenchResult};
+
+// ─── Units ─────────────────────────────────────────────────────────────────
+
+/// How a table picks its time unit (`--units`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Units {
+    /// Pick from the magnitude of the table's median row.
+    Auto,
+    Fixed(TimeUnit),
+}
+
+impl FromStr for Units {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Units, String> {
+        match s {
+            "auto" => Ok(Units::Auto),
+            other => other.parse().map(Units::Fixed)
+                .map_err(|_| format!("unknown unit '{}' (expected ns, us, ms, s or auto)", other)),
+        }
+    }
+}
+
+// ─── Planning ──────────────────────────────────────────────────────────────
+
+const NAME_MIN_WIDTH: usize = 24;
+const TIME_MIN_WIDTH: usize = 10;
+const SIZE_WIDTH: usize = 8;
+const ENERGY_WIDTH: usize = 10;
+const TIME_LABELS: [&str; 4] = ["Min", "Median", "Mean", "Max"];
+
+pub struct TablePlan {
+    pub unit: TimeUnit,
+    name_width: usize,
+    time_width: usize,
+    sigma_width: usize,
+    /// Whether any row carries energy readings; adds two columns.
+    energy: bool,
+}
+
+impl TablePlan {
+    pub fn new(rows: &[&BenchResult], units: Units) -> TablePlan {
+        let unit = match units {
+            Units::Fixed(u) => u,
+            Units::Auto => {
+                let mut medians: Vec<Duration> = rows.iter().map(|r| r.median()).collect();
+                medians.sort();
+                medians.get(medians.len() / 2)
+                    .map_or(TimeUnit::Ms, |&d| TimeUnit::for_magnitude(d))
+            }
+        };
+
+        let mut plan = TablePlan {
+            unit,
+            name_width: NAME_MIN_WIDTH,
+            time_width: TIME_MIN_WIDTH,
+            sigma_width: SIZE_WIDTH,
+            energy: rows.iter().any(|r| !r.energy_uj.is_empty()),
+        };
+        plan.time_width = plan.time_width.max(plan.time_label("Median").chars().count());
+        plan.sigma_width = plan.sigma_width.max(plan.time_label("σ").chars().count());
+
+        for r in rows {
+            plan.name_width = plan.name_width.max(r.name.chars().count());
+            for cell in plan.time_cells(r) {
+                plan.time_width = plan.time_width.max(cell.len());
+            }
+            plan.sigma_width = plan.sigma_width.max(unit.format_nanos(r.stddev_ns()).len());
+        }
+        plan
+    }
+
+    fn time_label(&self, label: &str) -> String {
+        format!("{} ({})", label, self.unit.suffix())
+    }
+
+    fn time_cells(&self, r: &BenchResult) -> [String; 4] {
+        [r.min(), r.median(), r.mean(), r.max()]
+            .map(|d| self.unit.format_nanos(d.as_nanos() as f64))
+    }
+
+    /// Total width of a rendered line, in characters.
+    pub fn width(&self) -> usize {
+        // name + 4 time columns + σ + 2 size columns, joined by " │ "
+        let base = self.name_width + 4 * self.time_width + self.sigma_width + 2 * SIZE_WIDTH + 7 * 3;
+        if self.energy { base + 2 * (ENERGY_WIDTH + 3) } else { base }
+    }
+
+    fn line(&self, name: &str, times: &[String], sigma: &str, sieve: &str, result: &str, energy: [&str; 2]) -> String {
+        let mut out = format!("{:<w$}", name, w = self.name_width);
+        for t in times {
+            out.push_str(&format!(" │ {:>w$}", t, w = self.time_width));
+        }
+        out.push_str(&format!(" │ {:>w$}", sigma, w = self.sigma_width));
+        out.push_str(&format!(" │ {:>w$} │ {:>w$}", sieve, result, w = SIZE_WIDTH));
+        if self.energy {
+            out.push_str(&format!(" │ {:>w$} │ {:>w$}", energy[0], energy[1], w = ENERGY_WIDTH));
+        }
+        out.push('\n');
+        out
+    }
+
+    pub fn header(&self) -> String {
+        let labels: Vec<String> = TIME_LABELS.iter().map(|l| self.time_label(l)).collect();
+        let mut out = self.line("Implementation", &labels, &self.time_label("σ"), "Sieve", "Result", ["Energy", "Primes/J"]);
+        out.push_str(&"─".repeat(self.width()));
+        out.push('\n');
+        out
+    }
+
+    pub fn row(&self, r: &BenchResult) -> String {
+        let energy = r.median_energy_uj().map_or("n/a".to_string(), energy::format_microjoules);
+        let per_joule = r.primes_per_joule().map_or("n/a".to_string(), |p| format!("{:.0}", p));
+        let mut out = self.line(&r.name, &self.time_cells(r), &self.unit.format_nanos(r.stddev_ns()),
+            &r.sieve_bytes.map_or("n/a".to_string(), |b| Bytes(b as u64).to_string()),
+            &Bytes(r.result_bytes as u64).to_string(), [&energy, &per_joule]);
+        if let Some(note) = noise_note(r) {
+            out.pop();
+            out.push_str("  ");
+            out.push_str(&note);
+            out.push('\n');
+        }
+        out
+    }
+
+    /// A placeholder row for an implementation that was not run; `reason`
+    /// is split across the Mean and Max columns.
+    pub fn skipped_row(&self, name: &str, reason: (&str, &str)) -> String {
+        let times = ["—".to_string(), "skipped".to_string(), reason.0.to_string(), reason.1.to_string()];
+        self.line(name, &times, "—", "—", "—", ["—", "—"])
+    }
+
+    /// A row for an implementation that was not run at this n, showing an
+    /// extrapolated median as `~value` and `note` after the line.
+    pub fn estimated_row(&self, name: &str, median: Duration, note: &str) -> String {
+        let estimate = format!("~{}", self.unit.format_nanos(median.as_nanos() as f64));
+        let times = ["—".to_string(), estimate, "—".to_string(), "—".to_string()];
+        let mut out = self.line(name, &times, "—", "—", "—", ["—", "—"]);
+        out.pop();
+        out.push_str("  ");
+        out.push_str(note);
+        out.push('\n');
+        out
+    }
+
+    /// First call against the steady-state median, in the table's unit,
+    /// with `—` for the ratio over a zero median. `None` when the row ran
+    /// without warmup.
+    pub fn first_call_row(&self, r: &BenchResult) -> Option<String> {
+        let first = r.first_call()?;
+        let median = r.median();
+        Some(format!("{:<w$} │ first call {} {} vs median {} {} ({})\n",
+            r.name,
+            self.unit.format_nanos(first.as_nanos() as f64), self.unit.suffix(),
+            self.unit.format_nanos(median.as_nanos() as f64), self.unit.suffix(),
+            Ratio::of(first, median),
+            w = self.name_width))
+    }
+}
+
+/// Trailing annotation for rows that needed re-runs or stayed noisy.
+fn noise_note(r: &BenchResult) -> Option<String> {
+    let mad = Percent(r.stability());
+    match (r.noisy, r.reruns) {
+        (true, 0) => Some(format!("~ noisy (MAD {})", mad)),
+        (true, k) => Some(format!("~ noisy after {} re-run{} (MAD {})", k, if k == 1 { "" } else { "s" }, mad)),
+        (false, 0) => None,
+        (false, k) => Some(format!("⟳ steady after {} re-run{}", k, if k == 1 { "" } else { "s" })),
+    }
+}
+
+// ─── Speedup matrix ────────────────────────────────────────────────────────
+
+/// Pairwise median ratios: `ratio(i, j)` is how many times faster row `i`
+/// ran than column `j` (above 1 means `i` is faster).
+pub struct SpeedupMatrix {
+    names: Vec<String>,
+    medians: Vec<Duration>,
+}
+
+impl SpeedupMatrix {
+    pub fn new(medians: &[(&str, Duration)]) -> SpeedupMatrix {
+        SpeedupMatrix {
+            names: medians.iter().map(|(name, _)| name.to_string()).collect(),
+            medians: medians.iter().map(|&(_, d)| d).collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn ratio(&self, i: usize, j: usize) -> f64 {
+        self.medians[j].as_nanos() as f64 / self.medians[i].as_nanos() as f64
+    }
+
+    /// One line per row, names down the left and across the top, ratios
+    /// to two decimals and `—` on the diagonal or over a zero median. Empty
+    /// for an empty matrix.
+    pub fn render(&self) -> String {
+        if self.is_empty() {
+            return String::new();
+        }
+        let name_width = self.names.iter().map(|n| n.chars().count()).max().unwrap();
+        let col_width = |j: usize| self.names[j].chars().count().max(6);
+
+        let mut out = " ".repeat(name_width);
+        for (j, name) in self.names.iter().enumerate() {
+            out.push_str(&format!("  {:>w$}", name, w = col_width(j)));
+        }
+        out.push('\n');
+        for (i, name) in self.names.iter().enumerate() {
+            out.push_str(&format!("{:<w$}", name, w = name_width));
+            for j in 0..self.len() {
+                let cell = if i == j { Ratio(None) } else { Ratio::of(self.medians[j], self.medians[i]) };
+                out.push_str(&format!("  {:>w$}", cell, w = col_width(j)));
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_result;
+
+    fn synthetic(name: &str, times_ns: &[u64]) -> BenchResult {
+        BenchResult { sieve_bytes: Some(32 * 1024), result_bytes: 96, ..test_result(name, times_ns) }
+    }
+
+    #[test]
+    fn test_noise_annotations() {
+        let mut r = synthetic("wofl (flat)", &[1_000_000, 1_300_000, 900_000]);
+        let refs: Vec<&BenchResult> = vec![&r];
+        let plan = TablePlan::new(&refs, Units::Auto);
+        let clean = plan.row(&r);
+        assert!(clean.ends_with("96 B\n"));
+
+        r.reruns = 1;
+        assert_eq!(plan.row(&r), clean.replace('\n', "  ⟳ steady after 1 re-run\n"));
+        r.noisy = true;
+        r.reruns = 2;
+        assert_eq!(plan.row(&r), clean.replace('\n', "  ~ noisy after 2 re-runs (MAD 10.0%)\n"));
+        r.reruns = 0;
+        assert!(plan.row(&r).ends_with("96 B  ~ noisy (MAD 10.0%)\n"));
+    }
+
+    #[test]
+    fn test_unattributed_sieve_shows_na() {
+        let r = synthetic("primal (iterator)", &[1_000_000]).unattributed();
+        let refs: Vec<&BenchResult> = vec![&r];
+        assert!(TablePlan::new(&refs, Units::Auto).row(&r).ends_with("│      n/a │     96 B\n"));
+    }
+
+    #[test]
+    fn test_energy_columns_only_when_measured() {
+        let mut r = synthetic("wofl (flat)", &[1_000_000, 1_000_000, 1_000_000]);
+        r.prime_count = 1_000;
+        let refs: Vec<&BenchResult> = vec![&r];
+        let plain = TablePlan::new(&refs, Units::Auto);
+        assert!(!plain.header().contains("Energy"));
+
+        r.energy_uj = vec![2_000, 2_500, 3_000];
+        let refs: Vec<&BenchResult> = vec![&r];
+        let plan = TablePlan::new(&refs, Units::Auto);
+        assert!(plan.header().contains("│     Energy │   Primes/J\n"));
+        assert!(plan.row(&r).ends_with("96 B │     2.5 mJ │     400000\n"));
+        assert_eq!(plan.width(), plain.width() + 26);
+        assert!(plan.skipped_row("primes crate (iter)", ("(too", "slow)")).ends_with("│          — │          —\n"));
+    }
+
+    #[test]
+    fn test_estimated_row() {
+        let rows = [synthetic("wofl (segmented)", &[1_500_000, 2_000_000, 2_500_000])];
+        let refs: Vec<&BenchResult> = rows.iter().collect();
+        let plan = TablePlan::new(&refs, Units::Auto);
+        assert_eq!(plan.estimated_row("primes crate (iter)", Duration::from_millis(1_234), "(skipped; fit t ∝ n^1.10)"),
+            "primes crate (iter)      │           — │    ~1234.00 │           — │           — │        — │        — │        —  (skipped; fit t ∝ n^1.10)\n");
+    }
+
+    #[test]
+    fn test_speedup_matrix_ratios() {
+        let m = SpeedupMatrix::new(&[
+            ("flat", Duration::from_micros(3_000)),
+            ("segmented", Duration::from_micros(1_000)),
+            ("atomic", Duration::from_micros(1_500)),
+        ]);
+        assert_eq!(m.len(), 3);
+        assert_eq!(m.ratio(1, 0), 3.0);
+        assert_eq!(m.ratio(0, 1), 1.0 / 3.0);
+        assert_eq!(m.ratio(2, 1), 1_000.0 / 1_500.0);
+        assert_eq!(m.ratio(2, 2), 1.0);
+        assert_eq!(m.render(), concat!(
+            "             flat  segmented  atomic\n",
+            "flat            —      0.33x   0.50x\n",
+            "segmented   3.00x          —   1.50x\n",
+            "atomic      2.00x      0.67x       —\n",
+        ));
+    }
+
+    #[test]
+    fn test_speedup_matrix_degenerate() {
+        let single = SpeedupMatrix::new(&[("flat", Duration::from_micros(10))]);
+        assert_eq!(single.render(), "        flat\nflat       —\n");
+        assert!(SpeedupMatrix::new(&[]).is_empty());
+        assert_eq!(SpeedupMatrix::new(&[]).render(), "");
+
+        // A zero median has nothing to be divided by
+        let zero = SpeedupMatrix::new(&[("flat", Duration::from_micros(10)), ("seg", Duration::ZERO)]);
+        assert_eq!(zero.render(), concat!(
+            "        flat     seg\n",
+            "flat       —   0.00x\n",
+            "seg        —       —\n",
+        ));
+    }
+
+    #[test]
+    fn test_units_parse() {
+        assert_eq!("auto".parse::<Units>(), Ok(Units::Auto));
+        assert_eq!("ns".parse::<Units>(), Ok(Units::Fixed(TimeUnit::Ns)));
+        assert_eq!("us".parse::<Units>(), Ok(Units::Fixed(TimeUnit::Us)));
+        assert_eq!("µs".parse::<Units>(), Ok(Units::Fixed(TimeUnit::Us)));
+        assert_eq!("ms".parse::<Units>(), Ok(Units::Fixed(TimeUnit::Ms)));
+        assert!("minutes".parse::<Units>().is_err());
+    }
+
+    #[test]
+    fn test_auto_picks_unit_of_median_row() {
+        // One outlier in seconds must not drag the whole table to seconds.
+        let rows = [
+            synthetic("a", &[400_000]),
+            synthetic("b", &[2_000_000]),
+            synthetic("c", &[3_000_000]),
+            synthetic("d", &[5_000_000_000]),
+        ];
+        let refs: Vec<&BenchResult> = rows.iter().collect();
+        assert_eq!(TablePlan::new(&refs, Units::Auto).unit, TimeUnit::Ms);
+    }
+
+    #[test]
+    fn test_fixed_unit_overrides_auto() {
+        let rows = [synthetic("a", &[2_000_000])];
+        let refs: Vec<&BenchResult> = rows.iter().collect();
+        let plan = TablePlan::new(&refs, Units::Fixed(TimeUnit::Us));
+        assert_eq!(plan.unit, TimeUnit::Us);
+        assert!(plan.row(&rows[0]).contains("2000.0"));
+    }
+
+    #[test]
+    fn test_sigma_shares_time_unit() {
+        let rows = [synthetic("wofl (segmented)", &[1_500_000, 2_000_000, 2_500_000])];
+        let refs: Vec<&BenchResult> = rows.iter().collect();
+        let plan = TablePlan::new(&refs, Units::Auto);
+        assert_eq!(plan.header(), concat!(
+            "Implementation           │    Min (ms) │ Median (ms) │   Mean (ms) │    Max (ms) │   σ (ms) │    Sieve │   Result\n",
+            "─────────────────────────────────────────────────────────────────────────────────────────────────────────────────\n",
+        ));
+        assert_eq!(plan.row(&rows[0]),
+            "wofl (segmented)         │        1.50 │        2.00 │        2.00 │        2.50 │     0.41 │ 32.0 KiB │     96 B\n");
+    }
+
+    #[test]
+    fn test_first_call_row() {
+        let mut r = synthetic("wofl (flat)", &[2_000_000, 2_000_000, 2_000_000]);
+        let refs: Vec<&BenchResult> = vec![&r];
+        let plan = TablePlan::new(&refs, Units::Auto);
+        assert_eq!(plan.first_call_row(&r), None);
+
+        r.warmup_times = vec![Duration::from_millis(9), Duration::from_millis(3)];
+        assert_eq!(plan.first_call_row(&r).unwrap(),
+            "wofl (flat)              │ first call 9.00 ms vs median 2.00 ms (4.50x)\n");
+
+        let mut r = synthetic("wofl (flat)", &[0, 0, 0]);
+        r.warmup_times = vec![Duration::from_micros(40)];
+        assert_eq!(plan.first_call_row(&r).unwrap(),
+            "wofl (flat)              │ first call 0.04 ms vs median 0.00 ms (—)\n");
+    }
+
+    #[test]
+    fn test_nanosecond_table() {
+        let rows = [synthetic("tiny", &[800, 900, 1_000])];
+        let refs: Vec<&BenchResult> = rows.iter().collect();
+        let plan = TablePlan::new(&refs, Units::Auto);
+        assert_eq!(plan.unit, TimeUnit::Ns);
+        assert_eq!(plan.row(&rows[0]),
+            "tiny                     │         800 │         900 │         900 │        1000 │       82 │ 32.0 KiB │     96 B\n");
+    }
+
+    #[test]
+    fn test_minutes_stay_in_seconds() {
+        let rows = [synthetic("glacial", &[90_000_000_000, 120_000_000_000, 150_000_000_000])];
+        let refs: Vec<&BenchResult> = rows.iter().collect();
+        let plan = TablePlan::new(&refs, Units::Auto);
+        assert_eq!(plan.unit, TimeUnit::S);
+        assert_eq!(plan.row(&rows[0]),
+            "glacial                  │      90.00 │     120.00 │     120.00 │     150.00 │    24.49 │ 32.0 KiB │     96 B\n");
+    }
+
+    #[test]
+    fn test_columns_widen_for_large_values() {
+        // Forcing ns on a multi-second row produces 10-digit cells; every
+        // line must still have the same width.
+        let rows = [synthetic("slow", &[3_000_000_000]), synthetic("fast", &[1_000])];
+        let refs: Vec<&BenchResult> = rows.iter().collect();
+        let plan = TablePlan::new(&refs, Units::Fixed(TimeUnit::Ns));
+        let header = plan.header();
+        let header_line = header.lines().next().unwrap();
+        for r in &rows {
+            assert_eq!(plan.row(r).trim_end().chars().count(), header_line.chars().count());
+        }
+        assert!(plan.row(&rows[0]).contains("3000000000"));
+    }
+
+    #[test]
+    fn test_skipped_row_aligns_with_plan() {
+        let rows = [synthetic("wofl (flat)", &[1_500_000])];
+        let refs: Vec<&BenchResult> = rows.iter().collect();
+        let plan = TablePlan::new(&refs, Units::Auto);
+        assert_eq!(plan.skipped_row("primes crate (iter)", ("(too slow", "for n>1M)")),
+            "primes crate (iter)      │           — │     skipped │   (too slow │   for n>1M) │        — │        — │        —\n");
+    }
+
+    #[test]
+    fn test_empty_table_defaults_to_ms() {
+        assert_eq!(TablePlan::new(&[], Units::Auto).unit, TimeUnit::Ms);
+    }
+}
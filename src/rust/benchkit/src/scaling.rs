@@ -0,0 +1,96 @@
+//! Power-law extrapolation of run time against n.
+//!
+//! Used to give a skipped row a rough figure: fit t = c·n^k through the
+//! sizes an implementation did complete and read the fit off at the
+//! skipped n. Such figures are printed with a leading `~` and never enter
+//! speedup calculations.
+
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PowerFit {
+    /// k in t = c·n^k.
+    pub exponent: f64,
+    /// ln c, with t in nanoseconds.
+    pub ln_coefficient: f64,
+    /// Number of (n, t) points the fit was made from.
+    pub points: usize,
+}
+
+impl PowerFit {
+    /// Least-squares line through (ln n, ln t). Needs at least two distinct
+    /// n with nonzero times; otherwise there is no estimate.
+    pub fn fit(samples: &[(u64, Duration)]) -> Option<PowerFit> {
+        let points: Vec<(f64, f64)> = samples.iter()
+            .filter(|&&(n, t)| n > 0 && !t.is_zero())
+            .map(|&(n, t)| ((n as f64).ln(), (t.as_nanos() as f64).ln()))
+            .collect();
+        if points.len() < 2 {
+            return None;
+        }
+        let len = points.len() as f64;
+        let mean_x = points.iter().map(|p| p.0).sum::<f64>() / len;
+        let mean_y = points.iter().map(|p| p.1).sum::<f64>() / len;
+        let sxx: f64 = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+        if sxx == 0.0 {
+            return None;
+        }
+        let sxy: f64 = points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum();
+        let exponent = sxy / sxx;
+        Some(PowerFit {
+            exponent,
+            ln_coefficient: mean_y - exponent * mean_x,
+            points: points.len(),
+        })
+    }
+
+    pub fn predict(&self, n: u64) -> Duration {
+        let nanos = (self.ln_coefficient + self.exponent * (n as f64).ln()).exp();
+        Duration::from_nanos(nanos.min(u64::MAX as f64) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn synthetic(c: f64, k: f64, sizes: &[u64]) -> Vec<(u64, Duration)> {
+        sizes.iter().map(|&n| (n, Duration::from_nanos((c * (n as f64).powf(k)) as u64))).collect()
+    }
+
+    #[test]
+    fn test_recovers_known_exponents() {
+        let sizes = [10_000, 100_000, 500_000, 1_000_000];
+        for k in [1.0, 1.1, 1.5, 2.0] {
+            let fit = PowerFit::fit(&synthetic(40.0, k, &sizes)).unwrap();
+            assert!((fit.exponent - k).abs() < 1e-3, "k={} fit={}", k, fit.exponent);
+            assert_eq!(fit.points, 4);
+
+            let expected = 40.0 * 1e8f64.powf(k);
+            let got = fit.predict(100_000_000).as_nanos() as f64;
+            assert!((got / expected - 1.0).abs() < 1e-2, "k={}: {} vs {}", k, got, expected);
+        }
+    }
+
+    #[test]
+    fn test_noisy_samples_fit_in_between() {
+        // ±10% noise around t = n: exponent stays close to 1.
+        let samples = [
+            (10_000, Duration::from_nanos(11_000)),
+            (100_000, Duration::from_nanos(90_000)),
+            (1_000_000, Duration::from_nanos(1_100_000)),
+        ];
+        let fit = PowerFit::fit(&samples).unwrap();
+        assert!((fit.exponent - 1.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_needs_two_distinct_sizes() {
+        assert_eq!(PowerFit::fit(&[]), None);
+        assert_eq!(PowerFit::fit(&[(1_000, Duration::from_micros(5))]), None);
+        let same_n = [(1_000, Duration::from_micros(5)), (1_000, Duration::from_micros(6))];
+        assert_eq!(PowerFit::fit(&same_n), None);
+        let zero_time = [(1_000, Duration::ZERO), (2_000, Duration::from_micros(6))];
+        assert_eq!(PowerFit::fit(&zero_time), None);
+    }
+}
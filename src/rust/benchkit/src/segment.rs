@@ -0,0 +1,29 @@
+//! The L1 segment layout of `primer_core::segment`, re-exported for the
+//! bench binaries, plus the description their headers print. Printouts go
+//! through `describe` so the labels cannot drift from the numbers.
+
+pub use primer_core::segment::{SEGMENT_BITS, SEGMENT_BYTES, SEGMENT_SPAN, SEGMENT_WORDS};
+
+use crate::units::Bytes;
+use crate::Grouping;
+
+/// One-line description of the segment for report headers, e.g.
+/// `32.0 KiB (4,096 u64 words, 262,144 odd numbers spanning 524,288 integers)`.
+pub fn describe(digits: Grouping) -> String {
+    format!("{} ({} u64 words, {} odd numbers spanning {} integers)",
+        Bytes(SEGMENT_BYTES as u64),
+        digits.format(SEGMENT_WORDS as u64),
+        digits.format(SEGMENT_BITS),
+        digits.format(SEGMENT_SPAN))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_default_segment() {
+        assert_eq!(describe(Grouping::default()),
+            "32.0 KiB (4,096 u64 words, 262,144 odd numbers spanning 524,288 integers)");
+    }
+}
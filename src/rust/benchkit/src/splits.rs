@@ -0,0 +1,196 @@
+//! Split timing for iterator-based sieves.
+//!
+//! An iterator sieve run to the largest n passes every smaller n on the way.
+//! `SplitTimer` notes the elapsed time as each threshold is crossed, so one
+//! run yields estimates for every size. They are estimates: later work can
+//! perturb earlier splits (allocation growth, cache state), which is why
+//! `bench_splits` labels its rows as such.
+
+use std::time::{Duration, Instant};
+
+use crate::BenchResult;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Split {
+    pub threshold: u64,
+    /// Time from the start of the run until the first value above
+    /// `threshold` arrived: everything ≤ `threshold` produced and consumed,
+    /// plus producing that one extra value.
+    pub elapsed: Duration,
+    /// How many values ≤ `threshold` were produced.
+    pub count: usize,
+}
+
+/// Iterator adaptor that records a `Split` when the first value above each
+/// threshold arrives, or when the inner iterator ends.
+pub struct SplitTimer<I> {
+    inner: I,
+    start: Instant,
+    thresholds: Vec<u64>,
+    splits: Vec<Split>,
+    count: usize,
+}
+
+impl<I: Iterator<Item = u64>> SplitTimer<I> {
+    /// Starts the clock. `thresholds` must be in increasing order.
+    pub fn new(inner: I, thresholds: &[u64]) -> SplitTimer<I> {
+        assert!(thresholds.windows(2).all(|w| w[0] < w[1]),
+            "SplitTimer: thresholds must be strictly increasing");
+        SplitTimer {
+            inner,
+            start: Instant::now(),
+            thresholds: thresholds.to_vec(),
+            splits: Vec::with_capacity(thresholds.len()),
+            count: 0,
+        }
+    }
+
+    fn record_through(&mut self, value: Option<u64>) {
+        let elapsed = self.start.elapsed();
+        while let Some(&t) = self.thresholds.get(self.splits.len()) {
+            if value.is_some_and(|v| v <= t) {
+                break;
+            }
+            self.splits.push(Split { threshold: t, elapsed, count: self.count });
+        }
+    }
+
+    /// Splits for every threshold; any not yet crossed are closed at the
+    /// current time.
+    pub fn finish(mut self) -> Vec<Split> {
+        self.record_through(None);
+        self.splits
+    }
+}
+
+impl<I: Iterator<Item = u64>> Iterator for SplitTimer<I> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        let item = self.inner.next();
+        let pending = self.thresholds.get(self.splits.len()).copied();
+        match (item, pending) {
+            (Some(v), Some(t)) if v > t => self.record_through(item),
+            (None, Some(_)) => self.record_through(None),
+            _ => {}
+        }
+        if item.is_some() {
+            self.count += 1;
+        }
+        item
+    }
+}
+
+/// Like `bench`, but each call of `f` runs once to the largest threshold and
+/// returns its output plus one split per threshold. Produces one result per
+/// threshold, named `"<name> (est.)"`.
+pub fn bench_splits<F>(name: &str, thresholds: &[u64], iterations: usize, warmup: usize, f: F) -> Vec<BenchResult>
+where
+    F: Fn(&[u64]) -> (Vec<u64>, Vec<Split>),
+{
+    let mut results: Vec<BenchResult> = thresholds.iter().map(|&n| BenchResult {
+        name: format!("{} (est.)", name),
+        n,
+        prime_count: 0,
+        times: Vec::with_capacity(iterations),
+        sieve_bytes: None,
+        result_bytes: 0,
+        output: Vec::new(),
+        warmup_times: Vec::with_capacity(warmup),
+        energy_uj: Vec::new(),
+        reruns: 0,
+        noisy: false,
+    }).collect();
+
+    for i in 0..warmup + iterations {
+        let (output, splits) = f(thresholds);
+        assert_eq!(splits.len(), thresholds.len(), "bench_splits: one split per threshold");
+        for (r, split) in results.iter_mut().zip(&splits) {
+            if i < warmup {
+                r.warmup_times.push(split.elapsed);
+            } else {
+                r.times.push(split.elapsed);
+                r.prime_count = split.count;
+                r.result_bytes = split.count * std::mem::size_of::<u64>();
+            }
+        }
+        if i + 1 == warmup + iterations {
+            for (r, split) in results.iter_mut().zip(&splits) {
+                r.output = output[..split.count].to_vec();
+            }
+        }
+        std::hint::black_box(output);
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Yields 1..=limit, sleeping `pause` before every value above `slow_from`.
+    fn synthetic(limit: u64, slow_from: u64, pause: Duration) -> impl Iterator<Item = u64> {
+        (1..=limit).inspect(move |&v| {
+            if v > slow_from {
+                std::thread::sleep(pause);
+            }
+        })
+    }
+
+    #[test]
+    fn test_split_counts_and_order() {
+        let mut timer = SplitTimer::new(synthetic(100, 100, Duration::ZERO), &[5, 10, 50]);
+        let taken: Vec<u64> = timer.by_ref().take_while(|&v| v <= 50).collect();
+        assert_eq!(taken.len(), 50);
+
+        let splits = timer.finish();
+        let counts: Vec<(u64, usize)> = splits.iter().map(|s| (s.threshold, s.count)).collect();
+        assert_eq!(counts, vec![(5, 5), (10, 10), (50, 50)]);
+        assert!(splits.windows(2).all(|w| w[0].elapsed <= w[1].elapsed));
+    }
+
+    #[test]
+    fn test_split_times_track_slow_tail() {
+        // Values above 10 each cost 5 ms. The split at 10 pays for one of
+        // them (producing 11 is what reveals the crossing), the split at 20
+        // for eleven.
+        let mut timer = SplitTimer::new(synthetic(30, 10, Duration::from_millis(5)), &[10, 20]);
+        let _: Vec<u64> = timer.by_ref().take_while(|&v| v <= 20).collect();
+        let splits = timer.finish();
+        assert!(splits[1].elapsed >= Duration::from_millis(55));
+        assert!(splits[0].elapsed < splits[1].elapsed / 4);
+    }
+
+    #[test]
+    fn test_thresholds_beyond_end_close_on_finish() {
+        let mut timer = SplitTimer::new(1..=7u64, &[3, 100, 1_000]);
+        assert_eq!(timer.by_ref().count(), 7);
+        let counts: Vec<usize> = timer.finish().iter().map(|s| s.count).collect();
+        assert_eq!(counts, vec![3, 7, 7]);
+    }
+
+    #[test]
+    fn test_multiple_thresholds_in_one_gap() {
+        // 2 → 11 jumps over both 5 and 7.
+        let mut timer = SplitTimer::new([2u64, 11, 13].into_iter(), &[5, 7, 11]);
+        assert_eq!(timer.by_ref().count(), 3);
+        let counts: Vec<(u64, usize)> = timer.finish().iter().map(|s| (s.threshold, s.count)).collect();
+        assert_eq!(counts, vec![(5, 1), (7, 1), (11, 2)]);
+    }
+
+    #[test]
+    fn test_bench_splits_builds_labelled_rows() {
+        let rows = bench_splits("counter", &[10, 40], 3, 1, |thresholds| {
+            let max = *thresholds.last().unwrap();
+            let mut timer = SplitTimer::new(1..=100u64, thresholds);
+            let out: Vec<u64> = timer.by_ref().take_while(|&v| v <= max).collect();
+            (out, timer.finish())
+        });
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].name, "counter (est.)");
+        assert_eq!((rows[0].n, rows[0].prime_count, rows[0].times.len()), (10, 10, 3));
+        assert_eq!(rows[0].warmup_times.len(), 1);
+        assert_eq!(rows[0].output, (1..=10).collect::<Vec<u64>>());
+        assert_eq!((rows[1].n, rows[1].prime_count), (40, 40));
+    }
+}
@@ -0,0 +1,103 @@
+//! Noise detection and automatic re-runs of unstable rows.
+//!
+//! A row's stability score is MAD / median of its timed samples (see
+//! `BenchResult::stability`). Rows scoring above the policy's threshold are
+//! re-run, and the steadiest sample set seen is the one reported.
+
+use crate::BenchResult;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RetryPolicy {
+    /// Largest acceptable MAD / median, as a fraction (0.05 = 5%).
+    pub threshold: f64,
+    /// Extra runs allowed per row; 0 disables re-running.
+    pub max_reruns: usize,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy { threshold: 0.05, max_reruns: 2 }
+    }
+}
+
+impl RetryPolicy {
+    pub fn is_noisy(&self, r: &BenchResult) -> bool {
+        r.stability() > self.threshold
+    }
+
+    /// Call `attempt` once, then again while the steadiest result so far is
+    /// noisy and re-runs remain. The returned result records how many
+    /// re-runs happened and whether it is still noisy.
+    pub fn run<F>(&self, mut attempt: F) -> BenchResult
+    where
+        F: FnMut() -> BenchResult,
+    {
+        let mut best = attempt();
+        let mut reruns = 0;
+        while reruns < self.max_reruns && self.is_noisy(&best) {
+            best = most_stable(best, attempt());
+            reruns += 1;
+        }
+        best.reruns = reruns;
+        best.noisy = self.is_noisy(&best);
+        best
+    }
+}
+
+/// The result with the lower stability score; `a` wins ties.
+pub fn most_stable(a: BenchResult, b: BenchResult) -> BenchResult {
+    if b.stability() < a.stability() { b } else { a }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_result;
+
+    fn result(times_ns: &[u64]) -> BenchResult {
+        test_result("row", times_ns)
+    }
+
+    const CLEAN: [u64; 5] = [1_000, 1_010, 990, 1_000, 1_005];
+    const NOISY: [u64; 5] = [1_000, 1_600, 700, 1_300, 900];
+    const NOISIER: [u64; 5] = [1_000, 2_500, 400, 1_900, 300];
+
+    #[test]
+    fn test_clean_row_runs_once() {
+        let mut calls = 0;
+        let r = RetryPolicy::default().run(|| { calls += 1; result(&CLEAN) });
+        assert_eq!((calls, r.reruns, r.noisy), (1, 0, false));
+    }
+
+    #[test]
+    fn test_noisy_row_rerun_until_clean() {
+        let mut attempts = vec![result(&CLEAN), result(&NOISY)];
+        let r = RetryPolicy::default().run(|| attempts.pop().unwrap());
+        assert_eq!(r.times, result(&CLEAN).times);
+        assert_eq!((r.reruns, r.noisy), (1, false));
+    }
+
+    #[test]
+    fn test_persistently_noisy_keeps_steadiest_set() {
+        let mut attempts = vec![result(&NOISIER), result(&NOISY), result(&NOISIER)];
+        let r = RetryPolicy::default().run(|| attempts.pop().unwrap());
+        assert!(attempts.is_empty());
+        assert_eq!(r.times, result(&NOISY).times);
+        assert_eq!((r.reruns, r.noisy), (2, true));
+    }
+
+    #[test]
+    fn test_zero_reruns_only_flags() {
+        let policy = RetryPolicy { max_reruns: 0, ..RetryPolicy::default() };
+        let mut calls = 0;
+        let r = policy.run(|| { calls += 1; result(&NOISY) });
+        assert_eq!((calls, r.reruns, r.noisy), (1, 0, true));
+    }
+
+    #[test]
+    fn test_most_stable_prefers_first_on_tie() {
+        let a = BenchResult { name: "a".to_string(), ..result(&CLEAN) };
+        let b = BenchResult { name: "b".to_string(), ..result(&CLEAN) };
+        assert_eq!(most_stable(a, b).name, "a");
+    }
+}
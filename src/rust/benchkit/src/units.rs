@@ -0,0 +1,406 @@
+//! Durations, byte sizes and percentages: one formatter and one parser each.
+//! Ratios of two durations only print.
+//!
+//! Every wrapper prints through `Formatter::pad`, so width and alignment
+//! flags work as they do for strings, and parses back what it prints
+//! (to within the digits shown).
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+// ─── Time units ────────────────────────────────────────────────────────────
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeUnit {
+    Ns,
+    Us,
+    Ms,
+    S,
+}
+
+impl TimeUnit {
+    pub fn suffix(self) -> &'static str {
+        match self {
+            TimeUnit::Ns => "ns",
+            TimeUnit::Us => "µs",
+            TimeUnit::Ms => "ms",
+            TimeUnit::S => "s",
+        }
+    }
+
+    fn nanos(self) -> f64 {
+        match self {
+            TimeUnit::Ns => 1.0,
+            TimeUnit::Us => 1e3,
+            TimeUnit::Ms => 1e6,
+            TimeUnit::S => 1e9,
+        }
+    }
+
+    fn decimals(self) -> usize {
+        match self {
+            TimeUnit::Ns => 0,
+            TimeUnit::Us => 1,
+            TimeUnit::Ms | TimeUnit::S => 2,
+        }
+    }
+
+    fn next(self) -> Option<TimeUnit> {
+        match self {
+            TimeUnit::Ns => Some(TimeUnit::Us),
+            TimeUnit::Us => Some(TimeUnit::Ms),
+            TimeUnit::Ms => Some(TimeUnit::S),
+            TimeUnit::S => None,
+        }
+    }
+
+    /// The largest unit in which `d` is still ≥ 1.
+    pub fn for_magnitude(d: Duration) -> TimeUnit {
+        let nanos = d.as_nanos();
+        if nanos < 1_000 {
+            TimeUnit::Ns
+        } else if nanos < 1_000_000 {
+            TimeUnit::Us
+        } else if nanos < 1_000_000_000 {
+            TimeUnit::Ms
+        } else {
+            TimeUnit::S
+        }
+    }
+
+    /// `nanos` expressed in this unit, without a suffix.
+    pub fn format_nanos(self, nanos: f64) -> String {
+        format!("{:.*}", self.decimals(), nanos / self.nanos())
+    }
+}
+
+impl FromStr for TimeUnit {
+    type Err = String;
+
+    /// Accepts `u` and both the micro sign and the Greek mu for microseconds.
+    fn from_str(s: &str) -> Result<TimeUnit, String> {
+        match s {
+            "ns" => Ok(TimeUnit::Ns),
+            "us" | "µs" | "μs" => Ok(TimeUnit::Us),
+            "ms" => Ok(TimeUnit::Ms),
+            "s" => Ok(TimeUnit::S),
+            other => Err(format!("unknown time unit '{}' (expected ns, us, ms or s)", other)),
+        }
+    }
+}
+
+// ─── Parsing helpers ───────────────────────────────────────────────────────
+
+/// Split `"12.5 ms"` into `(12.5, "ms")`; the space is optional.
+fn split_quantity(s: &str) -> Result<(f64, &str), String> {
+    let s = s.trim();
+    let at = s.find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+'))
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(at);
+    let value: f64 = number.parse()
+        .ok()
+        .filter(|v: &f64| v.is_finite())
+        .ok_or_else(|| format!("invalid number in '{}'", s))?;
+    Ok((value, unit.trim_start()))
+}
+
+/// Whether `value`, once rounded to `decimals` places, is still below
+/// `limit`. When it is not, the formatters move up a unit, so 999,999 ns
+/// prints as `1.00 ms` rather than `1000.0 µs`.
+fn rounds_below(value: f64, decimals: usize, limit: f64) -> bool {
+    let p = 10f64.powi(decimals as i32);
+    (value * p).round() / p < limit
+}
+
+// ─── Durations ─────────────────────────────────────────────────────────────
+
+/// A duration printed in the largest unit that keeps it below 1000:
+/// `999 ns`, `1.0 µs`, `999.9 µs`, `1.00 ms`, `2.50 s`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Elapsed(pub Duration);
+
+impl fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let nanos = self.0.as_nanos() as f64;
+        let mut unit = TimeUnit::for_magnitude(self.0);
+        while let Some(next) = unit.next() {
+            if rounds_below(nanos / unit.nanos(), unit.decimals(), 1000.0) {
+                break;
+            }
+            unit = next;
+        }
+        f.pad(&format!("{} {}", unit.format_nanos(nanos), unit.suffix()))
+    }
+}
+
+impl FromStr for Elapsed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Elapsed, String> {
+        let (value, unit) = split_quantity(s)?;
+        if unit.is_empty() {
+            return Err(format!("duration '{}' needs a unit (ns, us, ms or s)", s.trim()));
+        }
+        let unit: TimeUnit = unit.parse()?;
+        if value < 0.0 {
+            return Err(format!("negative duration '{}'", s.trim()));
+        }
+        Ok(Elapsed(Duration::from_nanos((value * unit.nanos()).round() as u64)))
+    }
+}
+
+// ─── Byte sizes ────────────────────────────────────────────────────────────
+
+const BINARY_SUFFIXES: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+const DECIMAL_SUFFIXES: [&str; 4] = ["kB", "MB", "GB", "TB"];
+
+/// A byte count. Prints in binary units (`32.0 KiB`); the alternate flag
+/// (`{:#}`) prints decimal units (`32.8 kB`) instead. Parsing takes either
+/// and never guesses: `KB`/`kB` is 1000 bytes, `KiB` is 1024.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bytes(pub u64);
+
+impl fmt::Display for Bytes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (base, suffixes) = if f.alternate() {
+            (1000.0, DECIMAL_SUFFIXES)
+        } else {
+            (1024.0, BINARY_SUFFIXES)
+        };
+        if (self.0 as f64) < base {
+            return f.pad(&format!("{} B", self.0));
+        }
+        let mut value = self.0 as f64 / base;
+        let mut i = 0;
+        while i + 1 < suffixes.len() && !rounds_below(value, 1, base) {
+            value /= base;
+            i += 1;
+        }
+        f.pad(&format!("{:.1} {}", value, suffixes[i]))
+    }
+}
+
+impl FromStr for Bytes {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Bytes, String> {
+        let (value, unit) = split_quantity(s)?;
+        let scale = match unit {
+            "" | "B" => 1u64,
+            "kB" | "KB" => 1_000,
+            "MB" => 1_000_000,
+            "GB" => 1_000_000_000,
+            "TB" => 1_000_000_000_000,
+            "KiB" => 1 << 10,
+            "MiB" => 1 << 20,
+            "GiB" => 1 << 30,
+            "TiB" => 1 << 40,
+            other => return Err(format!("unknown size unit '{}' (expected B, kB, MB, GB, KiB, MiB or GiB)", other)),
+        };
+        let bytes = (value * scale as f64).round();
+        if value < 0.0 || bytes >= u64::MAX as f64 {
+            return Err(format!("size '{}' out of range", s.trim()));
+        }
+        Ok(Bytes(bytes as u64))
+    }
+}
+
+// ─── Percentages ───────────────────────────────────────────────────────────
+
+/// A ratio printed as a percentage: `Percent(0.05)` is `5.0%`. Parsing
+/// takes `5`, `5%` or `5.0 %` alike.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Percent(pub f64);
+
+impl fmt::Display for Percent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(&format!("{:.1}%", self.0 * 100.0))
+    }
+}
+
+impl FromStr for Percent {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Percent, String> {
+        let (value, unit) = split_quantity(s)?;
+        match unit {
+            "" | "%" => Ok(Percent(value / 100.0)),
+            other => Err(format!("unexpected '{}' after percentage", other)),
+        }
+    }
+}
+
+// ─── Ratios ────────────────────────────────────────────────────────────────
+
+/// One duration over another, printed as `2.50x`. `None` when the
+/// denominator is zero, which prints as `—`: a median below the timer's
+/// resolution says nothing about relative speed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ratio(pub Option<f64>);
+
+impl Ratio {
+    pub fn of(num: Duration, den: Duration) -> Ratio {
+        if den.is_zero() {
+            return Ratio(None);
+        }
+        Ratio(Some(num.as_nanos() as f64 / den.as_nanos() as f64))
+    }
+}
+
+impl fmt::Display for Ratio {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Some(r) => f.pad(&format!("{:.2}x", r)),
+            None => f.pad("—"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fixed spread of values over many orders of magnitude.
+    fn samples() -> impl Iterator<Item = u64> {
+        let mut x: u64 = 0x9e37_79b9_7f4a_7c15;
+        (0..2_000).map(move |i| {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            x >> (i % 60)
+        })
+    }
+
+    #[test]
+    fn test_for_magnitude_boundaries() {
+        assert_eq!(TimeUnit::for_magnitude(Duration::from_nanos(999)), TimeUnit::Ns);
+        assert_eq!(TimeUnit::for_magnitude(Duration::from_nanos(1_000)), TimeUnit::Us);
+        assert_eq!(TimeUnit::for_magnitude(Duration::from_nanos(999_999)), TimeUnit::Us);
+        assert_eq!(TimeUnit::for_magnitude(Duration::from_millis(1)), TimeUnit::Ms);
+        assert_eq!(TimeUnit::for_magnitude(Duration::from_secs(120)), TimeUnit::S);
+    }
+
+    #[test]
+    fn test_elapsed_units() {
+        let show = |ns| Elapsed(Duration::from_nanos(ns)).to_string();
+        assert_eq!(show(0), "0 ns");
+        assert_eq!(show(999), "999 ns");
+        assert_eq!(show(1_000), "1.0 µs");
+        assert_eq!(show(999_949), "999.9 µs");
+        assert_eq!(show(1_000_000), "1.00 ms");
+        assert_eq!(show(2_500_000_000), "2.50 s");
+        assert_eq!(format!("[{:>9}]", Elapsed(Duration::from_micros(1_500))), "[  1.50 ms]");
+    }
+
+    #[test]
+    fn test_elapsed_rounding_boundaries() {
+        // Rounding up to 1000 of a unit moves to the next unit
+        let show = |ns| Elapsed(Duration::from_nanos(ns)).to_string();
+        assert_eq!(show(999_950), "1.00 ms");
+        assert_eq!(show(999_999), "1.00 ms");
+        assert_eq!(show(999_994_999), "999.99 ms");
+        assert_eq!(show(999_995_000), "1.00 s");
+        assert_eq!(show(5_000_000_000_000), "5000.00 s");
+    }
+
+    #[test]
+    fn test_elapsed_parse() {
+        let parse = |s: &str| s.parse::<Elapsed>().map(|e| e.0);
+        assert_eq!(parse("10ms"), Ok(Duration::from_millis(10)));
+        assert_eq!(parse("10 ms"), Ok(Duration::from_millis(10)));
+        assert_eq!(parse("1.5us"), Ok(Duration::from_nanos(1_500)));
+        assert_eq!(parse("1.5 µs"), Ok(Duration::from_nanos(1_500)));
+        assert_eq!(parse("1.5 μs"), Ok(Duration::from_nanos(1_500)));
+        assert_eq!(parse("999999 ns"), Ok(Duration::from_nanos(999_999)));
+        assert_eq!(parse("2 s"), Ok(Duration::from_secs(2)));
+        assert!(parse("10").is_err());
+        assert!(parse("ms").is_err());
+        assert!(parse("-1 ms").is_err());
+        assert!(parse("10 min").is_err());
+    }
+
+    #[test]
+    fn test_elapsed_round_trip() {
+        for ns in samples().map(|x| x % 10_000_000_000_000) {
+            let shown = Elapsed(Duration::from_nanos(ns)).to_string();
+            let back = shown.parse::<Elapsed>().unwrap().0.as_nanos() as f64;
+            let unit: TimeUnit = shown.rsplit(' ').next().unwrap().parse().unwrap();
+            // Within half of the last digit shown
+            let slack = unit.nanos() * 0.5 / 10f64.powi(unit.decimals() as i32);
+            assert!((back - ns as f64).abs() <= slack, "{} ns shown as {}", ns, shown);
+        }
+    }
+
+    #[test]
+    fn test_bytes_units() {
+        assert_eq!(Bytes(0).to_string(), "0 B");
+        assert_eq!(Bytes(1023).to_string(), "1023 B");
+        assert_eq!(Bytes(1024).to_string(), "1.0 KiB");
+        assert_eq!(Bytes(32 * 1024).to_string(), "32.0 KiB");
+        assert_eq!(Bytes(64_200_000).to_string(), "61.2 MiB");
+        assert_eq!(Bytes((1 << 20) - 1).to_string(), "1.0 MiB");
+        assert_eq!(Bytes(3 << 30).to_string(), "3.0 GiB");
+        assert_eq!(format!("{:#}", Bytes(999)), "999 B");
+        assert_eq!(format!("{:#}", Bytes(32 * 1024)), "32.8 kB");
+        assert_eq!(format!("{:#}", Bytes(64_200_000)), "64.2 MB");
+        assert_eq!(format!("[{:>9}]", Bytes(96)), "[     96 B]");
+    }
+
+    #[test]
+    fn test_bytes_parse_is_explicit() {
+        let parse = |s: &str| s.parse::<Bytes>().map(|b| b.0);
+        assert_eq!(parse("96"), Ok(96));
+        assert_eq!(parse("96 B"), Ok(96));
+        assert_eq!(parse("32KB"), Ok(32_000));
+        assert_eq!(parse("32 kB"), Ok(32_000));
+        assert_eq!(parse("32 KiB"), Ok(32_768));
+        assert_eq!(parse("1.5 GiB"), Ok(3 << 29));
+        assert_eq!(parse("2 GB"), Ok(2_000_000_000));
+        assert!(parse("32 kb").is_err());
+        assert!(parse("-1 B").is_err());
+        assert!(parse("20000000 TiB").is_err());
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        for b in samples().map(|x| x % (1 << 45)) {
+            for alternate in [false, true] {
+                let shown = if alternate { format!("{:#}", Bytes(b)) } else { Bytes(b).to_string() };
+                let back = shown.parse::<Bytes>().unwrap().0 as f64;
+                // One decimal shown: within half a tenth of the unit printed
+                let unit = format!("1 {}", shown.rsplit(' ').next().unwrap()).parse::<Bytes>().unwrap().0;
+                let slack = unit as f64 * 0.05 + 0.5;
+                assert!((back - b as f64).abs() <= slack, "{} shown as {}", b, shown);
+            }
+        }
+    }
+
+    #[test]
+    fn test_percent() {
+        assert_eq!(Percent(0.05).to_string(), "5.0%");
+        assert_eq!(Percent(0.1234).to_string(), "12.3%");
+        assert_eq!("5".parse::<Percent>(), Ok(Percent(0.05)));
+        assert_eq!("5%".parse::<Percent>(), Ok(Percent(0.05)));
+        assert_eq!("12.5 %".parse::<Percent>(), Ok(Percent(0.125)));
+        assert!("5 pct".parse::<Percent>().is_err());
+        assert!("%".parse::<Percent>().is_err());
+        for tenths in 0..2_000 {
+            let p = Percent(tenths as f64 / 1000.0);
+            let back: Percent = p.to_string().parse().unwrap();
+            assert!((back.0 - p.0).abs() < 1e-12, "{}", p);
+        }
+    }
+
+    #[test]
+    fn test_ratio() {
+        let us = Duration::from_micros;
+        assert_eq!(Ratio::of(us(9), us(2)), Ratio(Some(4.5)));
+        assert_eq!(Ratio::of(us(9), us(2)).to_string(), "4.50x");
+        assert_eq!(Ratio::of(us(1), us(3)).to_string(), "0.33x");
+        assert_eq!(Ratio::of(us(9), Duration::ZERO), Ratio(None));
+        assert_eq!(Ratio::of(Duration::ZERO, Duration::ZERO).to_string(), "—");
+        assert_eq!(format!("{:>6}", Ratio::of(us(3), us(1))), " 3.00x");
+        assert_eq!(format!("{:>6}", Ratio(None)), "     —");
+    }
+}
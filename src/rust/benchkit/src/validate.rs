@@ -0,0 +1,217 @@
+//! Post-run correctness checks between benchmark results.
+//!
+//! Validation only ever looks at finished `BenchResult`s: `bench` knows
+//! nothing about the level, so no checking work can land between timed
+//! samples whatever `--validate` says.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::BenchResult;
+
+// ─── Levels ────────────────────────────────────────────────────────────────
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// No checking at all.
+    Off,
+    /// Prime counts must agree (the historical behaviour).
+    #[default]
+    Counts,
+    /// Counts plus a digest of the full output.
+    Hashes,
+    /// Element-wise comparison, reporting the first divergent index.
+    Full,
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Level, String> {
+        match s {
+            "off" => Ok(Level::Off),
+            "counts" => Ok(Level::Counts),
+            "hashes" => Ok(Level::Hashes),
+            "full" => Ok(Level::Full),
+            other => Err(format!("unknown validate level '{}' (expected off, counts, hashes or full)", other)),
+        }
+    }
+}
+
+impl Level {
+    /// Whether `check` at this level reads `BenchResult::output`; below
+    /// `Hashes` only the counts are compared, so the output can be freed
+    /// with `without_output` as soon as the row is timed.
+    pub fn reads_output(self) -> bool {
+        self >= Level::Hashes
+    }
+}
+
+// ─── Mismatches ────────────────────────────────────────────────────────────
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Divergence {
+    Count { expected: usize, got: usize },
+    Digest { expected: u64, got: u64 },
+    /// First index where the outputs differ; `None` means that side ended.
+    Element { index: usize, expected: Option<u64>, got: Option<u64> },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Mismatch {
+    pub n: u64,
+    pub reference: String,
+    pub candidate: String,
+    pub divergence: Divergence,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MISMATCH at n={}: {} vs {}: ", self.n, self.reference, self.candidate)?;
+        match self.divergence {
+            Divergence::Count { expected, got } => {
+                write!(f, "count {} vs {}", expected, got)
+            }
+            Divergence::Digest { expected, got } => {
+                write!(f, "digest {:016x} vs {:016x}", expected, got)
+            }
+            Divergence::Element { index, expected, got } => {
+                let show = |v: Option<u64>| v.map_or("(end)".to_string(), |p| p.to_string());
+                write!(f, "first divergence at index {}: {} vs {}", index, show(expected), show(got))
+            }
+        }
+    }
+}
+
+// ─── Checking ──────────────────────────────────────────────────────────────
+
+/// FNV-1a over the little-endian bytes of every value.
+pub fn digest(values: &[u64]) -> u64 {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    for v in values {
+        for b in v.to_le_bytes() {
+            h ^= b as u64;
+            h = h.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+    h
+}
+
+/// Index of the first position where `a` and `b` differ, or `None` if they
+/// are identical. A length difference diverges at the shorter length.
+pub fn first_divergence(a: &[u64], b: &[u64]) -> Option<usize> {
+    match a.iter().zip(b).position(|(x, y)| x != y) {
+        Some(i) => Some(i),
+        None if a.len() != b.len() => Some(a.len().min(b.len())),
+        None => None,
+    }
+}
+
+/// Check `candidate` against `reference` to the depth `level` asks for.
+pub fn check(level: Level, reference: &BenchResult, candidate: &BenchResult) -> Result<(), Mismatch> {
+    let mismatch = |divergence| Mismatch {
+        n: reference.n,
+        reference: reference.name.clone(),
+        candidate: candidate.name.clone(),
+        divergence,
+    };
+
+    if level == Level::Off {
+        return Ok(());
+    }
+    if level == Level::Full {
+        let (a, b) = (&reference.output, &candidate.output);
+        return match first_divergence(a, b) {
+            Some(index) => Err(mismatch(Divergence::Element {
+                index,
+                expected: a.get(index).copied(),
+                got: b.get(index).copied(),
+            })),
+            None => Ok(()),
+        };
+    }
+
+    if reference.prime_count != candidate.prime_count {
+        return Err(mismatch(Divergence::Count {
+            expected: reference.prime_count,
+            got: candidate.prime_count,
+        }));
+    }
+    if level == Level::Hashes {
+        let (expected, got) = (digest(&reference.output), digest(&candidate.output));
+        if expected != got {
+            return Err(mismatch(Divergence::Digest { expected, got }));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_result;
+
+    fn result(name: &str, output: Vec<u64>) -> BenchResult {
+        BenchResult { n: 100, prime_count: output.len(), output, ..test_result(name, &[]) }
+    }
+
+    #[test]
+    fn test_parse_levels() {
+        assert_eq!("off".parse(), Ok(Level::Off));
+        assert_eq!("counts".parse(), Ok(Level::Counts));
+        assert_eq!("hashes".parse(), Ok(Level::Hashes));
+        assert_eq!("full".parse(), Ok(Level::Full));
+        assert!("all".parse::<Level>().is_err());
+        assert_eq!(Level::default(), Level::Counts);
+    }
+
+    #[test]
+    fn test_counts_pass_without_output() {
+        assert!(!Level::Off.reads_output() && !Level::Counts.reads_output());
+        assert!(Level::Hashes.reads_output() && Level::Full.reads_output());
+        let a = result("flat", vec![2, 3, 5, 7]).without_output();
+        let b = result("seg", vec![2, 3, 5, 7]).without_output();
+        assert_eq!(check(Level::Counts, &a, &b), Ok(()));
+    }
+
+    #[test]
+    fn test_full_pinpoints_single_divergence() {
+        let reference = result("flat", vec![2, 3, 5, 7, 11, 13]);
+        let candidate = result("seg", vec![2, 3, 5, 9, 11, 13]);
+
+        let err = check(Level::Full, &reference, &candidate).unwrap_err();
+        assert_eq!(err.divergence, Divergence::Element { index: 3, expected: Some(7), got: Some(9) });
+        assert_eq!(err.to_string(), "MISMATCH at n=100: flat vs seg: first divergence at index 3: 7 vs 9");
+
+        // Same length, so every shallower level lets it through except hashes.
+        assert!(check(Level::Counts, &reference, &candidate).is_ok());
+        assert!(check(Level::Off, &reference, &candidate).is_ok());
+        assert!(matches!(
+            check(Level::Hashes, &reference, &candidate).unwrap_err().divergence,
+            Divergence::Digest { .. }
+        ));
+    }
+
+    #[test]
+    fn test_full_reports_truncated_output() {
+        let reference = result("flat", vec![2, 3, 5, 7]);
+        let candidate = result("seg", vec![2, 3, 5]);
+
+        let err = check(Level::Full, &reference, &candidate).unwrap_err();
+        assert_eq!(err.divergence, Divergence::Element { index: 3, expected: Some(7), got: None });
+        assert!(err.to_string().ends_with("index 3: 7 vs (end)"));
+        assert_eq!(
+            check(Level::Counts, &reference, &candidate).unwrap_err().divergence,
+            Divergence::Count { expected: 4, got: 3 }
+        );
+    }
+
+    #[test]
+    fn test_identical_outputs_pass_every_level() {
+        let a = result("flat", vec![2, 3, 5, 7]);
+        let b = result("seg", vec![2, 3, 5, 7]);
+        for level in [Level::Off, Level::Counts, Level::Hashes, Level::Full] {
+            assert_eq!(check(level, &a, &b), Ok(()));
+        }
+    }
+}
@@ -1,63 +1,24 @@
 //! Prime Sieve Benchmark Harness
 //! Compares: wofl bit-packed sieve vs `primes` crate vs `primal` crate
 //!
-//! Usage: cargo run --release
-
-use std::time::{Duration, Instant};
-use std::fmt;
+//! Usage: cargo run --release [-- --units ns|us|ms|s|auto] [--warmup N]
+//!                              [--digit-separator comma|dot|space|thin|underscore|apostrophe|none]
+//!                              [--sizes N...]
+//!
+//! `--warmup` sets the untimed calls made before each implementation's timed
+//! runs (default 2, 0 allowed). With warmup on, each section also shows the
+//! first call against the steady-state median.
 
-// ─── wofl's bit-packed sieve ───────────────────────────────────────────────
+use std::time::Duration;
 
-#[inline]
-fn isqrt(n: u64) -> u64 {
-    if n == 0 { return 0; }
-    let mut x = (n as f64).sqrt() as u64;
-    while x > 0 && x.checked_mul(x).map_or(true, |sq| sq > n) { x -= 1; }
-    while (x + 1).checked_mul(x + 1).map_or(false, |sq| sq <= n) { x += 1; }
-    x
-}
+use benchkit::alloc::{self, CountingAlloc};
+use benchkit::cli::CommonArgs;
+use benchkit::{bench, Bytes, Grouping, Ratio, TablePlan};
+use primer_core::sieve_primes;
 
-#[inline]
-fn prime_count_upper(n: u64) -> usize {
-    if n < 10 { return 4; }
-    let nf = n as f64;
-    (nf / nf.ln() * 1.15) as usize + 1
-}
-
-fn wofl_sieve(n: u64) -> Vec<u64> {
-    if n < 2 { return vec![]; }
-    let h = n / 2;
-    let num_words = ((h >> 6) + 1) as usize;
-    let mut b = vec![!0u64; num_words];
-    b[0] ^= 1;
-    let sqrt_n = isqrt(n);
-
-    for i in 1..=(sqrt_n / 2) {
-        if (b[(i >> 6) as usize] >> (i & 63)) & 1 == 1 {
-            let step = 2 * i + 1;
-            let mut j = 2 * i * (i + 1);
-            while j <= h {
-                b[(j >> 6) as usize] &= !(1u64 << (j & 63));
-                j += step;
-            }
-        }
-    }
-
-    let mut r = Vec::with_capacity(prime_count_upper(n));
-    r.push(2);
-    for (i, &word) in b.iter().enumerate() {
-        let base = ((i << 6) * 2 + 1) as u64;
-        if base > n { break; }
-        let mut w = word;
-        while w != 0 {
-            let tz = w.trailing_zeros() as usize;
-            let p = ((i << 6) + tz) * 2 + 1;
-            if (p as u64) <= n { r.push(p as u64); }
-            w &= w - 1;
-        }
-    }
-    r
-}
+// Every allocation is counted, so the memory columns are measured peaks.
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
 
 // ─── Wrappers for crate implementations ────────────────────────────────────
 
@@ -83,167 +44,115 @@ fn primal_crate_sieve_direct(n: u64) -> Vec<u64> {
         .collect()
 }
 
-// ─── Benchmarking machinery ────────────────────────────────────────────────
+// ─── Report rendering ──────────────────────────────────────────────────────
 
-#[allow(dead_code)]
-struct BenchResult {
-    name: String,
-    n: u64,
-    prime_count: usize,
-    times: Vec<Duration>,
-    sieve_bytes: usize,   // memory for the sieve structure
-    result_bytes: usize,  // memory for the result vec
+fn render_section_open(n: u64, iterations: usize, digits: Grouping) -> String {
+    format!("┌─ n = {} ({} iterations) ─────────────────────────────────────────────────────\n│\n",
+        digits.format(n), iterations)
 }
 
-impl BenchResult {
-    fn median(&self) -> Duration {
-        let mut sorted: Vec<Duration> = self.times.clone();
-        sorted.sort();
-        sorted[sorted.len() / 2]
-    }
-
-    fn min(&self) -> Duration {
-        *self.times.iter().min().unwrap()
-    }
-
-    fn max(&self) -> Duration {
-        *self.times.iter().max().unwrap()
-    }
-
-    fn mean(&self) -> Duration {
-        let total: Duration = self.times.iter().sum();
-        total / self.times.len() as u32
+/// The per-n summary: the agreed prime count, then each implementation's
+/// median against the fastest, or `—` when the fastest median is zero.
+fn render_summary(n: u64, prime_count: usize, medians: &[(&str, Duration)], digits: Grouping) -> String {
+    let mut out = String::from("│\n");
+    out.push_str(&format!("│  π({}) = {}   │  All implementations agree ✓\n",
+        digits.format(n), digits.format(prime_count as u64)));
+
+    let fastest = medians.iter().map(|&(_, d)| d).min().unwrap();
+    for (name, time) in medians {
+        let ratio = Ratio::of(*time, fastest);
+        match ratio.0 {
+            Some(r) if r <= 1.01 => out.push_str(&format!("│  {} : fastest 🏆\n", name)),
+            Some(_) => out.push_str(&format!("│  {} : {} slower\n", name, ratio)),
+            None => out.push_str(&format!("│  {} : {}\n", name, ratio)),
+        }
     }
 
-    fn stddev_us(&self) -> f64 {
-        let mean = self.mean().as_nanos() as f64;
-        let variance = self.times.iter()
-            .map(|t| {
-                let diff = t.as_nanos() as f64 - mean;
-                diff * diff
-            })
-            .sum::<f64>() / self.times.len() as f64;
-        variance.sqrt() / 1000.0 // convert to µs
-    }
+    out.push_str("│\n");
+    out.push_str("└──────────────────────────────────────────────────────────────────────────────────\n\n");
+    out
 }
 
-impl fmt::Display for BenchResult {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:<24} │ {:>10} │ {:>10} │ {:>10} │ {:>10} │ {:>8.1} │ {:>8} │ {:>8}",
-            self.name,
-            format_duration(self.min()),
-            format_duration(self.median()),
-            format_duration(self.mean()),
-            format_duration(self.max()),
-            self.stddev_us(),
-            format_bytes(self.sieve_bytes),
-            format_bytes(self.result_bytes),
-        )
-    }
+// ─── Memory ────────────────────────────────────────────────────────────────
+
+fn render_memory_report(n: u64, wofl_sieve_bytes: usize, wofl_result_bytes: usize, primal_sieve_bytes: usize,
+                        digits: Grouping) -> String {
+    let mut out = String::new();
+    out.push_str(&format!("📊 Memory Efficiency @ n={} (measured peaks)\n", digits.format(n)));
+    out.push_str("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━\n");
+    out.push_str(&format!("  wofl sieve array:   {:>10}\n", Bytes(wofl_sieve_bytes as u64)));
+    out.push_str(&format!("  wofl result vec:    {:>10}\n", Bytes(wofl_result_bytes as u64)));
+    out.push_str(&format!("  wofl total:         {:>10}\n", Bytes((wofl_sieve_bytes + wofl_result_bytes) as u64)));
+    out.push_str(&format!("  primal Sieve::new:  {:>10} (comparison)\n", Bytes(primal_sieve_bytes as u64)));
+    out
 }
 
-fn format_duration(d: Duration) -> String {
-    let nanos = d.as_nanos();
-    if nanos < 1_000 {
-        format!("{} ns", nanos)
-    } else if nanos < 1_000_000 {
-        format!("{:.1} µs", nanos as f64 / 1_000.0)
-    } else if nanos < 1_000_000_000 {
-        format!("{:.2} ms", nanos as f64 / 1_000_000.0)
-    } else {
-        format!("{:.2} s", nanos as f64 / 1_000_000_000.0)
-    }
-}
+// ─── Command line ──────────────────────────────────────────────────────────
 
-fn format_bytes(b: usize) -> String {
-    if b < 1024 {
-        format!("{} B", b)
-    } else if b < 1024 * 1024 {
-        format!("{:.1} KB", b as f64 / 1024.0)
-    } else {
-        format!("{:.1} MB", b as f64 / (1024.0 * 1024.0))
-    }
-}
+const USAGE: &str = "usage: prime_bench [--units ns|us|ms|s|auto] [--warmup N]
+           [--digit-separator comma|dot|space|thin|underscore|apostrophe|none] [--sizes N...]";
 
-fn bench<F>(name: &str, n: u64, iterations: usize, sieve_bytes: usize, f: F) -> BenchResult
-where
-    F: Fn(u64) -> Vec<u64>,
-{
-    // Warmup
-    let _ = f(n);
-    let _ = f(n);
-
-    let mut times = Vec::with_capacity(iterations);
-    let mut prime_count = 0;
-    let mut result_bytes = 0;
-
-    for _ in 0..iterations {
-        let start = Instant::now();
-        let result = f(n);
-        let elapsed = start.elapsed();
-        prime_count = result.len();
-        result_bytes = result.capacity() * std::mem::size_of::<u64>();
-        times.push(elapsed);
-        // Prevent optimiser from eliding the computation
-        std::hint::black_box(&result);
-    }
+const DEFAULT_SIZES: [u64; 6] = [10_000, 100_000, 500_000, 1_000_000, 10_000_000, 50_000_000];
 
-    BenchResult {
-        name: name.to_string(),
-        n,
-        prime_count,
-        times,
-        sieve_bytes,
-        result_bytes,
+/// The shared flags are all prime_bench takes.
+fn parse_args<I: Iterator<Item = String>>(args: I) -> Result<CommonArgs, String> {
+    let mut parsed = CommonArgs::new(&DEFAULT_SIZES);
+    let mut args = args.peekable();
+    while let Some(arg) = args.next() {
+        if !parsed.parse_flag(&arg, &mut args)? {
+            return Err(format!("unknown argument '{}'", arg));
+        }
     }
-}
-
-fn print_header() {
-    println!("{:<24} │ {:>10} │ {:>10} │ {:>10} │ {:>10} │ {:>8} │ {:>8} │ {:>8}",
-        "Implementation", "Min", "Median", "Mean", "Max", "σ (µs)", "Sieve", "Result");
-    println!("{}", "─".repeat(115));
+    Ok(parsed)
 }
 
 fn main() {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("error: {}\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+    let digits = args.digits;
+
     println!("🦀 Prime Sieve Benchmark 🦀");
     println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
     println!();
 
-    let test_sizes: Vec<u64> = vec![
-        10_000,
-        100_000,
-        500_000,
-        1_000_000,
-        10_000_000,
-        50_000_000,
-    ];
-
     let iterations = 25;
 
-    for &n in &test_sizes {
-        println!("┌─ n = {} ({} iterations) ─────────────────────────────────────────────────────",
-            format_with_commas(n), iterations);
-        println!("│");
-
-        print_header();
+    for &n in &args.sizes {
+        print!("{}", render_section_open(n, iterations, digits));
 
-        // wofl sieve
-        let wofl_sieve_mem = ((n / 2 / 64 + 1) * 8) as usize;
-        let wofl = bench("wofl (bit-packed)", n, iterations, wofl_sieve_mem, wofl_sieve);
-        println!("{}", wofl);
+        // wofl sieve. Only the counts are checked below, so no row keeps
+        // its primes.
+        let wofl = bench("wofl (bit-packed)", n, iterations, args.warmup, sieve_primes).without_output();
 
-        // primes crate
-        let primes_res = bench("primes crate (iter)", n, iterations, 0, primes_crate_sieve);
-        println!("{}", primes_res);
+        // primes crate. The iterator rows grow their sieve and their output
+        // together, so their peaks can't be split between the two.
+        let primes_res = bench("primes crate (iter)", n, iterations, args.warmup, primes_crate_sieve).unattributed().without_output();
 
         // primal crate (iterator)
-        let primal_iter = bench("primal (iterator)", n, iterations, 0, primal_crate_sieve);
-        println!("{}", primal_iter);
+        let primal_iter = bench("primal (iterator)", n, iterations, args.warmup, primal_crate_sieve).unattributed().without_output();
 
         // primal crate (direct sieve)
-        let primal_sieve_mem = n as usize / 8; // approximate
-        let primal_direct = bench("primal (Sieve::new)", n, iterations, primal_sieve_mem, primal_crate_sieve_direct);
-        println!("{}", primal_direct);
+        let primal_direct = bench("primal (Sieve::new)", n, iterations, args.warmup, primal_crate_sieve_direct).without_output();
+
+        // One time unit for every column, planned from all four rows
+        let plan = TablePlan::new(&[&wofl, &primes_res, &primal_iter, &primal_direct], args.units);
+        print!("{}", plan.header());
+        for r in [&wofl, &primes_res, &primal_iter, &primal_direct] {
+            print!("{}", plan.row(r));
+        }
+        if args.warmup > 0 {
+            println!();
+            for r in [&wofl, &primes_res, &primal_iter, &primal_direct] {
+                if let Some(line) = plan.first_call_row(r) {
+                    print!("{}", line);
+                }
+            }
+        }
 
         // Verify all implementations agree on count
         assert_eq!(wofl.prime_count, primes_res.prime_count,
@@ -254,55 +163,74 @@ fn main() {
             "MISMATCH at n={}: wofl={} vs primal_direct={}", n, wofl.prime_count, primal_direct.prime_count);
 
         // Summary
-        println!("│");
-        let fastest = wofl.median().min(primes_res.median()).min(primal_iter.median()).min(primal_direct.median());
-        println!("│  π({}) = {}   │  All implementations agree ✓", 
-            format_with_commas(n), format_with_commas(wofl.prime_count as u64));
-
-        let speedups = [
+        let medians = [
             ("wofl", wofl.median()),
             ("primes", primes_res.median()),
             ("primal iter", primal_iter.median()),
             ("primal sieve", primal_direct.median()),
         ];
-        for (name, time) in &speedups {
-            let ratio = time.as_nanos() as f64 / fastest.as_nanos() as f64;
-            if ratio <= 1.01 {
-                println!("│  {} : fastest 🏆", name);
-            } else {
-                println!("│  {} : {:.2}x slower", name, ratio);
-            }
-        }
-
-        println!("│");
-        println!("└──────────────────────────────────────────────────────────────────────────────────");
-        println!();
+        print!("{}", render_summary(n, wofl.prime_count, &medians, digits));
     }
 
     // Memory efficiency comparison at n=10M
-    println!("📊 Memory Efficiency @ n=10,000,000");
-    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
     let n = 10_000_000u64;
-    let wofl_sieve_bytes = ((n / 2 / 64 + 1) * 8) as usize;
-    let wofl_result = wofl_sieve(n);
-    let wofl_result_bytes = wofl_result.capacity() * 8;
-    println!("  wofl sieve array:   {:>10}", format_bytes(wofl_sieve_bytes));
-    println!("  wofl result vec:    {:>10}", format_bytes(wofl_result_bytes));
-    println!("  wofl total:         {:>10}", format_bytes(wofl_sieve_bytes + wofl_result_bytes));
-    println!("  naive bool array:   {:>10} (comparison)", format_bytes(n as usize));
-    println!("  compression ratio:  {:>10.0}x vs naive", n as f64 / wofl_sieve_bytes as f64);
+    let (wofl_sieve_bytes, wofl_result_bytes) = alloc::measure_sieve_bytes(n, sieve_primes);
+    let (primal_sieve_bytes, _) = alloc::measure_sieve_bytes(n, primal_crate_sieve_direct);
+    print!("{}", render_memory_report(n, wofl_sieve_bytes, wofl_result_bytes, primal_sieve_bytes, digits));
     println!();
     println!("✓ Benchmark complete!");
 }
 
-fn format_with_commas(n: u64) -> String {
-    let s = n.to_string();
-    let mut result = String::new();
-    for (i, c) in s.chars().rev().enumerate() {
-        if i > 0 && i % 3 == 0 {
-            result.push(',');
-        }
-        result.push(c);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_section_open_snapshot() {
+        assert_eq!(render_section_open(10_000_000, 25, Grouping::default()), concat!(
+            "┌─ n = 10,000,000 (25 iterations) ─────────────────────────────────────────────────────\n",
+            "│\n",
+        ));
+    }
+
+    #[test]
+    fn test_summary_snapshot() {
+        let medians = [
+            ("wofl", Duration::from_micros(1_000)),
+            ("primes", Duration::from_micros(12_500)),
+            ("primal iter", Duration::from_micros(1_005)),
+            ("primal sieve", Duration::from_micros(2_000)),
+        ];
+        assert_eq!(render_summary(1_000_000, 78_498, &medians, Grouping::default()), concat!(
+            "│\n",
+            "│  π(1,000,000) = 78,498   │  All implementations agree ✓\n",
+            "│  wofl : fastest 🏆\n",
+            "│  primes : 12.50x slower\n",
+            "│  primal iter : fastest 🏆\n",
+            "│  primal sieve : 2.00x slower\n",
+            "│\n",
+            "└──────────────────────────────────────────────────────────────────────────────────\n",
+            "\n",
+        ));
+    }
+
+    #[test]
+    fn test_summary_zero_median() {
+        let medians = [("wofl", Duration::ZERO), ("primes", Duration::from_micros(40))];
+        let out = render_summary(1_000, 168, &medians, Grouping::default());
+        assert!(out.contains("│  wofl : —\n│  primes : —\n"), "{}", out);
     }
-    result.chars().rev().collect()
-}
\ No newline at end of file
+
+    #[test]
+    fn test_memory_report_snapshot() {
+        let report = render_memory_report(10_000_000, 625_008, 5_308_416, 1_310_720, Grouping::default());
+        assert_eq!(report, concat!(
+            "📊 Memory Efficiency @ n=10,000,000 (measured peaks)\n",
+            "━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━\n",
+            "  wofl sieve array:    610.4 KiB\n",
+            "  wofl result vec:       5.1 MiB\n",
+            "  wofl total:            5.7 MiB\n",
+            "  primal Sieve::new:     1.2 MiB (comparison)\n",
+        ));
+    }
+}
@@ -1,277 +1,242 @@
-/// Segmented Bit-Packed Sieve of Eratosthenes
-///
-/// Processes the sieve in L1-cache-sized segments (~32KB) to avoid
-/// cache thrashing on large n. Same bit-packing and Brian Kernighan
-/// tricks as the flat version, but 2-3x faster at n > 1M.
-
-use std::time::Instant;
-
-// ─── Tuning ────────────────────────────────────────────────────────────────
-
-/// Segment size in bits. 32KB = 262,144 bits = covers 524,288 odd numbers.
-/// Tune to L1d cache: x86_64 → 32KB, Apple M → 64KB, ESP32-S3 → 8KB.
-const SEGMENT_BITS: u64 = 32 * 1024 * 8;
-const SEGMENT_WORDS: usize = (SEGMENT_BITS / 64) as usize;
-
-// ─── Helpers ───────────────────────────────────────────────────────────────
-
-#[inline]
-fn isqrt(n: u64) -> u64 {
-    if n == 0 { return 0; }
-    let mut x = (n as f64).sqrt() as u64;
-    while x > 0 && x.checked_mul(x).map_or(true, |sq| sq > n) { x -= 1; }
-    while (x + 1).checked_mul(x + 1).map_or(false, |sq| sq <= n) { x += 1; }
-    x
+//! Segmented Bit-Packed Sieve of Eratosthenes
+//!
+//! Benchmarks primer-core's segmented sieve, which processes the range in
+//! L1-cache-sized segments (~32KB) to avoid cache thrashing on large n,
+//! against its flat sieve. Same bit-packing and Brian Kernighan tricks in
+//! both, but the segmented one is 2-3x faster at n > 1M.
+//!
+//! Usage: cargo run --release [-- --units ns|us|ms|s|auto] [--warmup N]
+//!                              [--digit-separator comma|dot|space|thin|underscore|apostrophe|none]
+//!                              [--sizes N...] [--stress-segments]
+//!
+//! `--units` prints every time in one unit; `auto` (the default) picks
+//! each time's own.
+//!
+//! `--warmup` sets the untimed calls made before each timed run (default 2,
+//! 0 allowed). With warmup on, each section also shows the first call
+//! against the steady-state median.
+//!
+//! `--stress-segments` skips the timings and instead re-sieves every size
+//! with tiny segments (512 B, 1000 B and 1 KiB), checking each output's
+//! digest against the default 32 KiB run and that the segments tiled the
+//! range. It prints a pass/fail line per (segment size, n) and exits 1 if
+//! any fail.
+
+use std::time::Duration;
+
+use benchkit::cli::CommonArgs;
+use benchkit::segment::{self, SEGMENT_BITS};
+use benchkit::validate::digest;
+use benchkit::{bench, BenchResult, Elapsed, Grouping, Ratio, Units};
+use primer_core::{segmented_sieve, segmented_sieve_checked, sieve_primes};
+
+// ─── Stress mode ───────────────────────────────────────────────────────────
+
+/// Segment sizes `--stress-segments` runs, in bytes. 1000 is deliberately
+/// not a power of two (it is still a whole number of words).
+const STRESS_SEGMENT_BYTES: [u64; 3] = [512, 1_000, 1_024];
+
+/// One (segment size, n) combination and how it went.
+#[derive(Debug, PartialEq, Eq)]
+struct StressCase {
+    segment_bytes: u64,
+    n: u64,
+    segments: usize,
+    /// `None` on a pass; otherwise what went wrong.
+    failure: Option<String>,
 }
 
-#[inline]
-fn prime_count_upper(n: u64) -> usize {
-    if n < 10 { return 4; }
-    let nf = n as f64;
-    (nf / nf.ln() * 1.15) as usize + 1
-}
-
-// ─── Small primes (seeds for segmented sieve) ─────────────────────────────
-
-fn sieve_small(limit: u64) -> Vec<u64> {
-    if limit < 2 { return vec![]; }
-    let h = limit / 2;
-    let num_words = ((h >> 6) + 1) as usize;
-    let mut b = vec![!0u64; num_words];
-    b[0] ^= 1;
-
-    let sq = isqrt(limit);
-    for i in 1..=(sq / 2) {
-        if (b[(i >> 6) as usize] >> (i & 63)) & 1 == 1 {
-            let step = 2 * i + 1;
-            let mut j = 2 * i * (i + 1);
-            while j <= h {
-                b[(j >> 6) as usize] &= !(1u64 << (j & 63));
-                j += step;
-            }
+/// Re-sieve each n at every stress segment size and compare digests with
+/// the default segment size.
+fn stress_segments(sizes: &[u64]) -> Vec<StressCase> {
+    let mut cases = Vec::new();
+    for &n in sizes {
+        let expected = digest(&segmented_sieve(n));
+        for &segment_bytes in &STRESS_SEGMENT_BYTES {
+            let (segments, failure) = match segmented_sieve_checked(n, segment_bytes * 8) {
+                Ok((primes, segments)) => {
+                    let got = digest(&primes);
+                    let failure = (got != expected)
+                        .then(|| format!("digest {:016x}, expected {:016x}", got, expected));
+                    (segments, failure)
+                }
+                Err(e) => (0, Some(e)),
+            };
+            cases.push(StressCase { segment_bytes, n, segments, failure });
         }
     }
+    cases
+}
 
-    let mut primes = Vec::new();
-    for (i, &word) in b.iter().enumerate() {
-        let mut w = word;
-        while w != 0 {
-            let tz = w.trailing_zeros() as usize;
-            let p = ((i << 6) + tz) * 2 + 1;
-            if (p as u64) <= limit { primes.push(p as u64); }
-            w &= w - 1;
+fn render_stress_report(cases: &[StressCase], digits: Grouping) -> String {
+    let mut out = String::from("🔬 Segment stress: tiny segments vs the default segment size\n");
+    for c in cases {
+        let label = format!("{:>5} B segments, n = {}", c.segment_bytes, digits.format(c.n));
+        match c.failure {
+            None => out.push_str(&format!("   {:<36} {:>12} segments  ✓\n", label, digits.format(c.segments as u64))),
+            Some(ref why) => out.push_str(&format!("   {:<36} FAIL: {}\n", label, why)),
         }
     }
-    primes
+    let failed = cases.iter().filter(|c| c.failure.is_some()).count();
+    if failed == 0 {
+        out.push_str(&format!("✓ all {} combinations agree\n", cases.len()));
+    } else {
+        out.push_str(&format!("✗ {} of {} combinations failed\n", failed, cases.len()));
+    }
+    out
 }
 
-// ─── Segmented sieve ──────────────────────────────────────────────────────
-
-pub fn sieve_primes_segmented(n: u64) -> Vec<u64> {
-    if n < 2 { return vec![]; }
-    if n < 3 { return vec![2]; }
-
-    let sqrt_n = isqrt(n);
-    let small_primes = sieve_small(sqrt_n);
-
-    let mut result = Vec::with_capacity(prime_count_upper(n));
-    result.push(2);
-
-    // Reusable segment buffer — fits in L1 cache
-    let mut segment = vec![0u64; SEGMENT_WORDS];
+// ─── Benchmark ─────────────────────────────────────────────────────────────
 
-    // Half-index space: index i → odd number 2*i+1
-    let h = n / 2;
+/// `d` in the `--units` unit, or in its own magnitude's with `auto`.
+fn format_time(d: Duration, units: Units) -> String {
+    match units {
+        Units::Auto => Elapsed(d).to_string(),
+        Units::Fixed(unit) => format!("{} {}", unit.format_nanos(d.as_nanos() as f64), unit.suffix()),
+    }
+}
 
-    // Track where each small prime's next composite falls
-    let mut next_composite: Vec<u64> = small_primes.iter().map(|&p| {
-        (p * p - 1) / 2  // half-index of p²
-    }).collect();
+fn render_section_open(n: u64, iters: usize, digits: Grouping) -> String {
+    let mut out = format!("┌─ n = {} ({} iters) ───────────────────────────────────────────────\n",
+        digits.format(n), iters);
+    out.push_str(&format!("│  {:<28} │ {:>10} │ {:>10} │\n", "Implementation", "Min", "Median"));
+    out.push_str(&format!("│  {}\n", "─".repeat(68)));
+    out
+}
 
-    let mut seg_start: u64 = 1;
+/// One timed implementation: min, median and its prime count.
+fn render_row(name: &str, min: Duration, median: Duration, prime_count: usize, args: &CommonArgs) -> String {
+    format!("│  {:<28} │ {:>10} │ {:>10} │ π = {}\n",
+        name, format_time(min, args.units), format_time(median, args.units),
+        args.digits.format(prime_count as u64))
+}
 
-    while seg_start <= h {
-        let seg_end = (seg_start + SEGMENT_BITS - 1).min(h);
-        let seg_len_bits = (seg_end - seg_start + 1) as usize;
-        let seg_len_words = (seg_len_bits + 63) / 64;
+/// `benchkit::bench` with `warmup` untimed calls, printed as one row.
+fn bench_row<F>(name: &str, n: u64, iters: usize, args: &CommonArgs, f: F) -> BenchResult
+where F: Fn(u64) -> Vec<u64>
+{
+    // Only the counts are compared, so the primes needn't be kept
+    let r = bench(name, n, iters, args.warmup, f).without_output();
+    print!("{}", render_row(name, r.min(), r.median(), r.prime_count, args));
+    r
+}
 
-        // Reset segment — all bits on (assume prime)
-        for w in segment[..seg_len_words].iter_mut() {
-            *w = !0u64;
-        }
+/// A row's first call against its steady-state median, `—` over a zero
+/// median.
+fn render_first_call(name: &str, first: Duration, median: Duration, units: Units) -> String {
+    format!("│  {:<28} │ first call {} vs median {} ({})\n",
+        name, format_time(first, units), format_time(median, units), Ratio::of(first, median))
+}
 
-        // Sieve with each small prime
-        for (pi, &p) in small_primes.iter().enumerate() {
-            let step = p;
-            let mut j = next_composite[pi];
+/// The segmented median against the flat one, closing the section. No
+/// verdict, just `—`, when the segmented median is zero.
+fn render_verdict(flat_median: Duration, seg_median: Duration) -> String {
+    let ratio = Ratio::of(flat_median, seg_median);
+    let verdict = match ratio.0 {
+        Some(r) if r > 1.1 => format!("{} faster 🏆", ratio),
+        Some(r) if r > 0.95 => format!("{} ~same ≈", ratio),
+        Some(_) => format!("{} slower 🐢", ratio),
+        None => ratio.to_string(),
+    };
+    let mut out = String::from("│\n");
+    out.push_str(&format!("│  Segmented is {}   │   counts match ✓\n", verdict));
+    out.push_str("└──────────────────────────────────────────────────────────────────────────\n\n");
+    out
+}
 
-            if j > seg_end { continue; }
+// ─── Command line ──────────────────────────────────────────────────────────
 
-            // Advance to start of this segment
-            if j < seg_start {
-                let gap = seg_start - j;
-                j += ((gap + step - 1) / step) * step;
-            }
+const USAGE: &str = "usage: seg_bench [--units ns|us|ms|s|auto] [--warmup N]
+                 [--digit-separator comma|dot|space|thin|underscore|apostrophe|none]
+                 [--sizes N...] [--stress-segments]";
 
-            // Mark composites
-            while j <= seg_end {
-                let local = (j - seg_start) as usize;
-                segment[local >> 6] &= !(1u64 << (local & 63));
-                j += step;
-            }
+const DEFAULT_SIZES: [u64; 7] = [
+    10_000, 100_000, 500_000, 1_000_000,
+    10_000_000, 50_000_000, 100_000_000,
+];
 
-            next_composite[pi] = j;
-        }
+struct Args {
+    common: CommonArgs,
+    stress_segments: bool,
+}
 
-        // Mask trailing bits in last word
-        let tail = seg_len_bits & 63;
-        if tail != 0 {
-            segment[seg_len_words - 1] &= (1u64 << tail) - 1;
+fn parse_args<I: Iterator<Item = String>>(args: I) -> Result<Args, String> {
+    let mut parsed = Args { common: CommonArgs::new(&DEFAULT_SIZES), stress_segments: false };
+    let mut args = args.peekable();
+    while let Some(arg) = args.next() {
+        if parsed.common.parse_flag(&arg, &mut args)? {
+            continue;
         }
-
-        // Extract primes — Brian Kernighan
-        for wi in 0..seg_len_words {
-            let mut w = segment[wi];
-            while w != 0 {
-                let tz = w.trailing_zeros() as u64;
-                let half_idx = seg_start + (wi as u64 * 64) + tz;
-                let p = half_idx * 2 + 1;
-                if p <= n { result.push(p); }
-                w &= w - 1;
-            }
+        match arg.as_str() {
+            "--stress-segments" => parsed.stress_segments = true,
+            other => return Err(format!("unknown argument '{}'", other)),
         }
-
-        seg_start += SEGMENT_BITS;
     }
-
-    result
+    Ok(parsed)
 }
 
-// ─── Flat sieve (original, for comparison) ─────────────────────────────────
-
-pub fn sieve_primes_flat(n: u64) -> Vec<u64> {
-    if n < 2 { return vec![]; }
-    let h = n / 2;
-    let num_words = ((h >> 6) + 1) as usize;
-    let mut b = vec![!0u64; num_words];
-    b[0] ^= 1;
-    let sqrt_n = isqrt(n);
-
-    for i in 1..=(sqrt_n / 2) {
-        if (b[(i >> 6) as usize] >> (i & 63)) & 1 == 1 {
-            let step = 2 * i + 1;
-            let mut j = 2 * i * (i + 1);
-            while j <= h {
-                b[(j >> 6) as usize] &= !(1u64 << (j & 63));
-                j += step;
-            }
-        }
-    }
-
-    let mut r = Vec::with_capacity(prime_count_upper(n));
-    r.push(2);
-    for (i, &word) in b.iter().enumerate() {
-        let base = ((i << 6) * 2 + 1) as u64;
-        if base > n { break; }
-        let mut w = word;
-        while w != 0 {
-            let tz = w.trailing_zeros() as usize;
-            let p = ((i << 6) + tz) * 2 + 1;
-            if (p as u64) <= n { r.push(p as u64); }
-            w &= w - 1;
+fn main() {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("error: {}\n{}", e, USAGE);
+            std::process::exit(2);
         }
-    }
-    r
-}
+    };
+    let digits = args.common.digits;
 
-// ─── Benchmark ─────────────────────────────────────────────────────────────
+    println!("🦀 Segmented vs Flat Sieve Benchmark 🦀");
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    println!("Segment size: {}", segment::describe(digits));
+    println!();
 
-fn format_commas(n: u64) -> String {
-    let s = n.to_string();
-    let mut r = String::new();
-    for (i, c) in s.chars().rev().enumerate() {
-        if i > 0 && i % 3 == 0 { r.push(','); }
-        r.push(c);
+    if args.stress_segments {
+        let cases = stress_segments(&args.common.sizes);
+        print!("{}", render_stress_report(&cases, digits));
+        if cases.iter().any(|c| c.failure.is_some()) {
+            std::process::exit(1);
+        }
+        return;
     }
-    r.chars().rev().collect()
-}
 
-fn format_dur(d: std::time::Duration) -> String {
-    let ns = d.as_nanos();
-    if ns < 1_000 { format!("{} ns", ns) }
-    else if ns < 1_000_000 { format!("{:.1} µs", ns as f64 / 1e3) }
-    else if ns < 1_000_000_000 { format!("{:.2} ms", ns as f64 / 1e6) }
-    else { format!("{:.2} s", ns as f64 / 1e9) }
-}
+    let iters = 25;
 
-fn bench<F>(name: &str, n: u64, iters: usize, f: F) -> (std::time::Duration, usize)
-where F: Fn(u64) -> Vec<u64>
-{
-    let _ = f(n); let _ = f(n); // warmup
-    let mut times = Vec::with_capacity(iters);
-    let mut count = 0;
-    for _ in 0..iters {
-        let t = Instant::now();
-        let r = f(n);
-        times.push(t.elapsed());
-        count = r.len();
-        std::hint::black_box(&r);
-    }
-    times.sort();
-    let min = times[0];
-    let med = times[times.len() / 2];
-    println!("│  {:<28} │ {:>10} │ {:>10} │ π = {}",
-        name, format_dur(min), format_dur(med), format_commas(count as u64));
-    (med, count)
-}
+    for &n in &args.common.sizes {
+        print!("{}", render_section_open(n, iters, digits));
 
-fn main() {
-    println!("🦀 Segmented vs Flat Sieve Benchmark 🦀");
-    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-    println!("Segment size: {}KB ({} u64 words, covers {} odd numbers per segment)",
-        SEGMENT_BITS / 64/ 1024, SEGMENT_WORDS, SEGMENT_BITS * 8);
-    println!();
+        let flat = bench_row("wofl flat", n, iters, &args.common, sieve_primes);
+        let seg = bench_row("wofl segmented", n, iters, &args.common, segmented_sieve);
+        if args.common.warmup > 0 {
+            println!("│");
+            for r in [&flat, &seg] {
+                print!("{}", render_first_call(&r.name, r.first_call().unwrap(), r.median(), args.common.units));
+            }
+        }
 
-    let sizes: Vec<u64> = vec![
-        10_000, 100_000, 500_000, 1_000_000,
-        10_000_000, 50_000_000, 100_000_000,
-    ];
-    let iters = 25;
+        assert_eq!(flat.prime_count, seg.prime_count, "COUNT MISMATCH at n={}", n);
 
-    for &n in &sizes {
-        println!("┌─ n = {} ({} iters) ───────────────────────────────────────────────",
-            format_commas(n), iters);
-        println!("│  {:<28} │ {:>10} │ {:>10} │", "Implementation", "Min", "Median");
-        println!("│  {}", "─".repeat(68));
-
-        let (flat_med, flat_c) = bench("wofl flat", n, iters, sieve_primes_flat);
-        let (seg_med, seg_c)   = bench("wofl segmented", n, iters, sieve_primes_segmented);
-
-        assert_eq!(flat_c, seg_c, "COUNT MISMATCH at n={}", n);
-
-        let ratio = flat_med.as_nanos() as f64 / seg_med.as_nanos() as f64;
-        let (label, emoji) = if ratio > 1.1 { ("faster", "🏆") }
-            else if ratio > 0.95 { ("~same", "≈") }
-            else { ("slower", "🐢") };
-        println!("│");
-        println!("│  Segmented is {:.2}x {} {}   │   counts match ✓", ratio, label, emoji);
-        println!("└──────────────────────────────────────────────────────────────────────────");
-        println!();
+        print!("{}", render_verdict(flat.median(), seg.median()));
     }
 
     // Full correctness
     println!("🔬 Full correctness at n=1,000,000...");
-    let flat = sieve_primes_flat(1_000_000);
-    let seg = sieve_primes_segmented(1_000_000);
+    let flat = sieve_primes(1_000_000);
+    let seg = segmented_sieve(1_000_000);
     assert_eq!(flat, seg, "FULL MISMATCH");
-    println!("   {} primes — byte-for-byte identical ✓", flat.len());
+    println!("   {} primes — byte-for-byte identical ✓", digits.format(flat.len() as u64));
     println!();
 
     println!("🔬 Full correctness at n=10,000,000...");
-    let flat = sieve_primes_flat(10_000_000);
-    let seg = sieve_primes_segmented(10_000_000);
+    let flat = sieve_primes(10_000_000);
+    let seg = segmented_sieve(10_000_000);
     assert_eq!(flat, seg, "FULL MISMATCH");
-    println!("   {} primes — byte-for-byte identical ✓", flat.len());
+    println!("   {} primes — byte-for-byte identical ✓", digits.format(flat.len() as u64));
+    println!();
+
+    println!("🔬 Segment coverage at n=10,000,000...");
+    let (checked, segments) = segmented_sieve_checked(10_000_000, SEGMENT_BITS).expect("SEGMENT COVERAGE");
+    assert_eq!(checked, seg, "FULL MISMATCH");
+    println!("   {} segments tile [1, n] with no gap or overlap ✓", digits.format(segments as u64));
     println!();
 
     println!("✓ Done!");
@@ -280,51 +245,91 @@ fn main() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use benchkit::TimeUnit;
 
     #[test]
-    fn test_segmented_small() {
-        assert_eq!(sieve_primes_segmented(10), vec![2, 3, 5, 7]);
-        assert_eq!(sieve_primes_segmented(20), vec![2, 3, 5, 7, 11, 13, 17, 19]);
+    fn test_parse_args() {
+        let args = |list: &[&str]| parse_args(list.iter().map(|s| s.to_string()));
+        let a = args(&[]).unwrap();
+        assert_eq!(a.common, CommonArgs::new(&DEFAULT_SIZES));
+        assert!(!a.stress_segments);
+        let a = args(&["--stress-segments", "--units", "ms", "--sizes", "10"]).unwrap();
+        assert!(a.stress_segments);
+        assert_eq!((a.common.units, a.common.sizes), (Units::Fixed(TimeUnit::Ms), vec![10]));
+        assert!(args(&["--bogus"]).is_err());
+        assert!(args(&["--warmup"]).is_err());
     }
 
     #[test]
-    fn test_segmented_edge_cases() {
-        assert_eq!(sieve_primes_segmented(0), vec![]);
-        assert_eq!(sieve_primes_segmented(1), vec![]);
-        assert_eq!(sieve_primes_segmented(2), vec![2]);
-        assert_eq!(sieve_primes_segmented(3), vec![2, 3]);
+    fn test_format_time_honours_units() {
+        let d = Duration::from_micros(1_500);
+        assert_eq!(format_time(d, Units::Auto), Elapsed(d).to_string());
+        assert_eq!(format_time(d, Units::Fixed(TimeUnit::Us)), "1500.0 µs");
+        assert_eq!(format_time(d, Units::Fixed(TimeUnit::Ms)), "1.50 ms");
     }
 
     #[test]
-    fn test_segmented_known_counts() {
-        assert_eq!(sieve_primes_segmented(100).len(), 25);
-        assert_eq!(sieve_primes_segmented(1_000).len(), 168);
-        assert_eq!(sieve_primes_segmented(10_000).len(), 1_229);
-        assert_eq!(sieve_primes_segmented(100_000).len(), 9_592);
-        assert_eq!(sieve_primes_segmented(500_000).len(), 41_538);
-        assert_eq!(sieve_primes_segmented(1_000_000).len(), 78_498);
+    fn test_section_snapshot() {
+        let args = CommonArgs::new(&DEFAULT_SIZES);
+        let ms = Duration::from_millis;
+        let mut section = render_section_open(1_000_000, 25, args.digits);
+        section.push_str(&render_row("wofl flat", ms(3), ms(4), 78_498, &args));
+        section.push_str(&render_row("wofl segmented", ms(1), ms(2), 78_498, &args));
+        section.push_str(&render_verdict(ms(4), ms(2)));
+        assert_eq!(section, concat!(
+            "┌─ n = 1,000,000 (25 iters) ───────────────────────────────────────────────\n",
+            "│  Implementation               │        Min │     Median │\n",
+            "│  ────────────────────────────────────────────────────────────────────\n",
+            "│  wofl flat                    │    3.00 ms │    4.00 ms │ π = 78,498\n",
+            "│  wofl segmented               │    1.00 ms │    2.00 ms │ π = 78,498\n",
+            "│\n",
+            "│  Segmented is 2.00x faster 🏆   │   counts match ✓\n",
+            "└──────────────────────────────────────────────────────────────────────────\n",
+            "\n",
+        ));
     }
 
     #[test]
-    fn test_matches_flat() {
-        for n in [10, 100, 1_000, 10_000, 100_000, 500_000, 1_000_000] {
-            assert_eq!(sieve_primes_flat(n), sieve_primes_segmented(n),
-                "Mismatch at n={}", n);
-        }
+    fn test_verdict_labels() {
+        let us = Duration::from_micros;
+        assert!(render_verdict(us(100), us(98)).contains("1.02x ~same ≈"));
+        assert!(render_verdict(us(100), us(200)).contains("0.50x slower 🐢"));
+        assert_eq!(render_verdict(us(100), Duration::ZERO).lines().nth(1),
+            Some("│  Segmented is —   │   counts match ✓"));
+    }
+
+    #[test]
+    fn test_first_call_line() {
+        let us = Duration::from_micros;
+        assert_eq!(render_first_call("wofl flat", us(900), us(200), Units::Fixed(TimeUnit::Us)),
+            "│  wofl flat                    │ first call 900.0 µs vs median 200.0 µs (4.50x)\n");
+        assert_eq!(render_first_call("wofl flat", us(900), Duration::ZERO, Units::Fixed(TimeUnit::Us)),
+            "│  wofl flat                    │ first call 900.0 µs vs median 0.0 µs (—)\n");
     }
 
     #[test]
-    fn test_segment_boundaries() {
-        // n that straddles segment boundary (~1,048,576)
-        let flat = sieve_primes_flat(1_100_000);
-        let seg = sieve_primes_segmented(1_100_000);
-        assert_eq!(flat, seg);
+    fn test_stress_segments_pass() {
+        let sizes = [3, 1_000, 65_537, 1_000_000];
+        let cases = stress_segments(&sizes);
+        assert_eq!(cases.len(), sizes.len() * STRESS_SEGMENT_BYTES.len());
+        assert!(cases.iter().all(|c| c.failure.is_none()), "{:?}", cases);
+        // 1,000,000 / 2 half-indices at 4,096 bits per segment
+        let c = cases.iter().find(|c| c.n == 1_000_000 && c.segment_bytes == 512).unwrap();
+        assert_eq!(c.segments, 123);
+        assert!(render_stress_report(&cases, Grouping::default()).ends_with("✓ all 12 combinations agree\n"));
     }
 
     #[test]
-    fn test_multi_segment() {
-        let flat = sieve_primes_flat(5_000_000);
-        let seg = sieve_primes_segmented(5_000_000);
-        assert_eq!(flat, seg);
+    fn test_stress_report_lists_failures() {
+        let cases = [
+            StressCase { segment_bytes: 512, n: 10_000, segments: 2, failure: None },
+            StressCase { segment_bytes: 1_000, n: 10_000, segments: 0, failure: Some("gap before segment 1".to_string()) },
+        ];
+        assert_eq!(render_stress_report(&cases, Grouping::default()), concat!(
+            "🔬 Segment stress: tiny segments vs the default segment size\n",
+            "     512 B segments, n = 10,000                    2 segments  ✓\n",
+            "    1000 B segments, n = 10,000         FAIL: gap before segment 1\n",
+            "✗ 1 of 2 combinations failed\n",
+        ));
     }
 }
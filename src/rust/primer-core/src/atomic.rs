@@ -0,0 +1,194 @@
+//! wofl's ATOMIC flat sieve (experimental, multi-threaded).
+//!
+//! Same bit layout as the flat sieve, but the words are AtomicU64 and the
+//! sieving primes are dealt round-robin to threads, which all strike into
+//! the one shared bitmap with fetch_and. Extraction is a serial pass after
+//! every thread has joined.
+//!
+//! This exists to measure the idea rather than to win: every thread streams
+//! the whole bitmap through its own cache, and words struck by several
+//! threads bounce between cores. Expect it to lose to the segmented sieve.
+//!
+//! Why Relaxed is enough:
+//!   - Strikes only ever clear bits, and fetch_and is a single atomic
+//!     read-modify-write, so two threads clearing bits in the same word can
+//!     never lose each other's update, whatever the ordering.
+//!   - Seeds come from a precomputed small sieve, not from the shared bitmap,
+//!     so no thread's control flow depends on what another has written.
+//!   - thread::scope joins every worker before extraction starts, and a join
+//!     is a happens-before edge, so the serial pass sees every strike.
+//!
+//! If a worker panics, it sets a shared abort flag that every worker checks
+//! before its next seed, so the rest stop promptly instead of finishing
+//! their share. The half-struck bitmap is then dropped, never extracted:
+//! a failure never comes back as a silently short prime list.
+
+use std::any::Any;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+
+use crate::flat::sieve_primes;
+use crate::half::{half_index_bound, value_of_half_index};
+use crate::{isqrt, prime_count_upper, MAX_N};
+
+/// A worker of the atomic sieve panicked while striking multiples of
+/// `seed`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct WorkerFailed {
+    pub worker: usize,
+    pub seed: u64,
+    pub cause: String,
+}
+
+impl fmt::Display for WorkerFailed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "worker {} failed on seed {}: {}", self.worker, self.seed, self.cause)
+    }
+}
+
+impl std::error::Error for WorkerFailed {}
+
+/// The message a panic was raised with, when it was a string.
+fn panic_cause(payload: &(dyn Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "non-string panic payload".to_string()
+    }
+}
+
+/// Every prime ≤ `n`, struck by `threads` workers (at least one) into one
+/// shared atomic bitmap. A worker's panic comes back as `WorkerFailed`,
+/// never as a short list.
+///
+/// Panics if `n` exceeds `MAX_N` (2^63).
+pub fn flat_atomic_sieve(n: u64, threads: usize) -> Result<Vec<u64>, WorkerFailed> {
+    flat_atomic_sieve_with(n, threads, |_| {})
+}
+
+/// `flat_atomic_sieve` with `on_seed` run in the worker before each seed
+/// is struck; tests use it to inject a failure. Not part of the supported
+/// API.
+#[doc(hidden)]
+pub fn flat_atomic_sieve_with<F>(n: u64, threads: usize, on_seed: F) -> Result<Vec<u64>, WorkerFailed>
+where
+    F: Fn(u64) + Sync,
+{
+    assert!(n <= MAX_N, "flat_atomic_sieve: n = {} exceeds the supported maximum 2^63", n);
+    if n < 2 { return Ok(vec![]); }
+    let h = half_index_bound(n);
+    let num_words = ((h >> 6) + 1) as usize;
+    let b: Vec<AtomicU64> = (0..num_words).map(|_| AtomicU64::new(!0u64)).collect();
+    b[0].fetch_and(!1, Ordering::Relaxed);
+
+    let seeds: Vec<u64> = sieve_primes(isqrt(n)).into_iter().filter(|&p| p > 2).collect();
+    let threads = threads.max(1);
+
+    let abort = AtomicBool::new(false);
+
+    thread::scope(|s| {
+        let workers: Vec<_> = (0..threads).map(|t| {
+            let (b, seeds, abort, on_seed) = (&b, &seeds, &abort, &on_seed);
+            s.spawn(move || {
+                for &p in seeds.iter().skip(t).step_by(threads) {
+                    if abort.load(Ordering::Relaxed) { break; }
+                    let struck = panic::catch_unwind(AssertUnwindSafe(|| {
+                        on_seed(p);
+                        let mut j = (p * p - 1) / 2;
+                        while j <= h {
+                            b[(j >> 6) as usize].fetch_and(!(1u64 << (j & 63)), Ordering::Relaxed);
+                            j += p;
+                        }
+                    }));
+                    if let Err(payload) = struck {
+                        abort.store(true, Ordering::Relaxed);
+                        return Err(WorkerFailed { worker: t, seed: p, cause: panic_cause(&*payload) });
+                    }
+                }
+                Ok(())
+            })
+        }).collect();
+        // Panics are caught above, so joining itself cannot fail
+        workers.into_iter().try_for_each(|w| w.join().unwrap())
+    })?;
+
+    let mut r = Vec::with_capacity(prime_count_upper(n));
+    r.push(2);
+    for (i, word) in b.iter().enumerate() {
+        if value_of_half_index((i << 6) as u64) > n { break; }
+        let mut w = word.load(Ordering::Relaxed);
+        while w != 0 {
+            let p = value_of_half_index(((i << 6) as u64) + w.trailing_zeros() as u64);
+            if p <= n { r.push(p); }
+            w &= w - 1;
+        }
+    }
+    Ok(r)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_matches_flat() {
+        for n in [0, 1, 2, 3, 10, 100, 1_000, 65_537, 1_000_000, 5_000_000] {
+            for threads in [1, 2, 3, 8] {
+                assert_eq!(flat_atomic_sieve(n, threads), Ok(sieve_primes(n)),
+                    "n={} threads={}", n, threads);
+            }
+        }
+    }
+
+    #[test]
+    fn test_zero_threads_runs_serially() {
+        assert_eq!(flat_atomic_sieve(10_000, 0), Ok(sieve_primes(10_000)));
+    }
+
+    #[test]
+    fn test_worker_panic_aborts_the_rest() {
+        // 445 odd seeds up to √10^7, dealt to 4 workers. Worker 0 fails on
+        // its first seed; without the abort flag the others would go on to
+        // start every remaining seed, ~2 ms apiece.
+        let started = AtomicUsize::new(0);
+        let start = Instant::now();
+        let err = flat_atomic_sieve_with(10_000_000, 4, |p| {
+            if p == 3 { panic!("injected failure at seed {}", p); }
+            started.fetch_add(1, Ordering::Relaxed);
+            thread::sleep(Duration::from_millis(2));
+        }).unwrap_err();
+
+        assert_eq!(err, WorkerFailed { worker: 0, seed: 3, cause: "injected failure at seed 3".to_string() });
+        assert_eq!(err.to_string(), "worker 0 failed on seed 3: injected failure at seed 3");
+        let started = started.load(Ordering::Relaxed);
+        assert!(started < 40, "{} seeds started after the failure", started);
+        assert!(start.elapsed() < Duration::from_secs(5), "took {:?}", start.elapsed());
+    }
+
+    #[test]
+    fn test_failure_never_returns_partial_primes() {
+        for threads in [1, 2, 8] {
+            let err = flat_atomic_sieve_with(1_000_000, threads, |p| {
+                if p == 997 { panic!("boom"); }
+            }).unwrap_err();
+            assert_eq!((err.seed, err.cause.as_str()), (997, "boom"), "threads={}", threads);
+        }
+        // String payloads come through as well as &str ones
+        let err = flat_atomic_sieve_with(1_000, 1, |p| {
+            if p == 5 { panic!("{}", String::from("owned")); }
+        }).unwrap_err();
+        assert_eq!(err.cause, "owned");
+    }
+
+    #[test]
+    #[should_panic(expected = "exceeds the supported maximum")]
+    fn test_rejects_n_above_cap() {
+        let _ = flat_atomic_sieve(MAX_N + 1, 1);
+    }
+}
@@ -0,0 +1,75 @@
+//! The original flat sieve: one bitmap for the whole range.
+
+use crate::half::{half_index_bound, value_of_half_index};
+use crate::{isqrt, prime_count_upper};
+
+/// All primes ≤ `n`, including 2, from a single bit-packed bitmap of
+/// n / 16 bytes. Fast while that fits in cache; the segmented sieve also
+/// uses it to find its seed primes up to √n.
+///
+/// Indices are u64 throughout, so n ≥ 2^32 is handled like any other n.
+/// The bitmap must still fit in memory. Where its word count overflows
+/// usize (32-bit targets, n ≥ 2^39), this panics up front rather than
+/// truncating.
+pub fn sieve_primes(n: u64) -> Vec<u64> {
+    if n < 2 { return vec![]; }
+    let h = half_index_bound(n);
+    let num_words = usize::try_from((h >> 6) + 1)
+        .unwrap_or_else(|_| panic!("sieve_primes: n = {} needs a bitmap larger than this target can address", n));
+
+    // All bits set → assume every odd number is prime; bit 0 is 1
+    let mut b = vec![!0u64; num_words];
+    b[0] ^= 1;
+    let sqrt_n = isqrt(n);
+
+    for i in 1..=half_index_bound(sqrt_n) {
+        if (b[(i >> 6) as usize] >> (i & 63)) & 1 == 1 {
+            let step = 2 * i + 1;
+            let mut j = 2 * i * (i + 1); // = (prime² - 1) / 2
+            while j <= h {
+                b[(j >> 6) as usize] &= !(1u64 << (j & 63));
+                j += step;
+            }
+        }
+    }
+
+    // Brian Kernighan: iterate only set bits
+    let mut r = Vec::with_capacity(prime_count_upper(n));
+    r.push(2);
+    for (i, &word) in b.iter().enumerate() {
+        if value_of_half_index((i << 6) as u64) > n { break; }
+        let mut w = word;
+        while w != 0 {
+            let p = value_of_half_index(((i << 6) as u64) + w.trailing_zeros() as u64);
+            if p <= n { r.push(p); }
+            w &= w - 1;
+        }
+    }
+    r
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tiny_limits() {
+        // Doubles as the seed sieve: it must include 2, and the segmented
+        // sieve drops it from the seeds itself.
+        assert_eq!(sieve_primes(0), vec![]);
+        assert_eq!(sieve_primes(1), vec![]);
+        assert_eq!(sieve_primes(2), vec![2]);
+        assert_eq!(sieve_primes(3), vec![2, 3]);
+        assert_eq!(sieve_primes(4), vec![2, 3]);
+        assert_eq!(sieve_primes(5), vec![2, 3, 5]);
+    }
+
+    #[test]
+    fn test_known_counts() {
+        assert_eq!(sieve_primes(100).len(), 25);
+        assert_eq!(sieve_primes(1_000).len(), 168);
+        assert_eq!(sieve_primes(10_000).len(), 1_229);
+        assert_eq!(sieve_primes(500_000).len(), 41_538);
+        assert_eq!(sieve_primes(1_000_000).len(), 78_498);
+    }
+}
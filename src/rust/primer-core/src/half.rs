@@ -0,0 +1,232 @@
+//! Conversions between values and half-indices in the odd-only layout.
+//!
+//! Every sieve here stores odd numbers only: half-index `i` stands for the
+//! value 2i + 1, so 0 → 1, 1 → 3, 2 → 5, and even values have no bit of
+//! their own. The sieves used to write `h = n / 2` for the last half-index
+//! to sieve up to an inclusive bound n. For odd n that is n itself, but for
+//! even n it is n + 1, one past the bound, and the extraction's `p <= n`
+//! filter was all that kept it out. `half_index_bound` is exact for both.
+//!
+//! The segment loops use the `HalfIdx` and `SegOffset` newtypes instead of
+//! raw u64s, so a value, a half-index and an offset into the segment
+//! buffer can't be mixed up; the only way across is a named conversion.
+
+/// Last half-index whose value is ≤ `n`: the one for `n` when it is odd,
+/// for n − 1 when it is even. So `half_index_bound(10)` is 4 (value 9, not
+/// 11) and `half_index_bound(11)` is 5.
+///
+/// For n < 1 there is no such half-index; this returns 0 (value 1), which
+/// is harmless because 1 is never prime and the sieves return early for
+/// n < 2 anyway.
+#[inline]
+pub fn half_index_bound(n: u64) -> u64 {
+    n.saturating_sub(1) / 2
+}
+
+/// The odd value half-index `h` stands for, 2h + 1. Every half-index of a
+/// u64 value is below 2^63, so this never overflows for those.
+#[inline]
+pub fn value_of_half_index(h: u64) -> u64 {
+    debug_assert!(h < 1 << 63, "half-index {} has no u64 value", h);
+    2 * h + 1
+}
+
+/// Half-index of `v` when it is odd. Even values, 2 included, are not in
+/// the odd-only layout and give `None`; the sieves emit 2 separately.
+#[inline]
+pub fn half_index_of_value(v: u64) -> Option<u64> {
+    (v % 2 == 1).then_some(v / 2)
+}
+
+// ─── Index types ───────────────────────────────────────────────────────────
+
+/// Half-index: bit position `i` standing for the odd value 2i + 1.
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct HalfIdx(u64);
+
+/// Bit offset within the current segment buffer.
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SegOffset(usize);
+
+impl HalfIdx {
+    /// Half-index of `v` when it is odd; `None` for even values, like
+    /// `half_index_of_value`.
+    #[inline]
+    pub fn of_value(v: u64) -> Option<HalfIdx> {
+        half_index_of_value(v).map(HalfIdx)
+    }
+
+    /// First half-index whose value is ≥ `v`: `v`'s own when it is odd,
+    /// the next odd value's when it is even. 0 and 1 both give 0 (value 1).
+    #[inline]
+    pub fn ceil(v: u64) -> HalfIdx {
+        HalfIdx(v / 2)
+    }
+
+    /// Last half-index whose value is ≤ `n`; see `half_index_bound`.
+    #[inline]
+    pub fn bound(n: u64) -> HalfIdx {
+        HalfIdx(half_index_bound(n))
+    }
+
+    /// The odd value this half-index stands for.
+    #[inline]
+    pub fn value(self) -> u64 {
+        value_of_half_index(self.0)
+    }
+
+    /// Offset of `self` inside a segment starting at `seg_start`.
+    #[inline]
+    pub fn local(self, seg_start: HalfIdx) -> SegOffset {
+        debug_assert!(self >= seg_start, "{:?} lies before segment start {:?}", self, seg_start);
+        SegOffset((self.0 - seg_start.0) as usize)
+    }
+
+    /// `k` bits further along.
+    #[inline]
+    pub fn advance(self, k: u64) -> HalfIdx {
+        HalfIdx(self.0 + k)
+    }
+
+    /// First position ≥ `lo` reachable from `self` in whole steps of
+    /// `step`, or `self` when it already lies at or past `lo`.
+    #[inline]
+    pub fn first_at_or_after(self, lo: HalfIdx, step: u64) -> HalfIdx {
+        if self >= lo { return self; }
+        HalfIdx(self.0 + (lo.0 - self.0).div_ceil(step) * step)
+    }
+}
+
+impl SegOffset {
+    /// Offset of bit `bit` in word `word` of the segment buffer.
+    #[inline]
+    pub fn from_word_bit(word: usize, bit: u32) -> SegOffset {
+        SegOffset((word << 6) + bit as usize)
+    }
+
+    #[inline]
+    pub fn index(self) -> usize {
+        self.0
+    }
+
+    /// Word of the segment buffer holding this bit.
+    #[inline]
+    pub fn word(self) -> usize {
+        self.0 >> 6
+    }
+
+    /// Single-bit mask for this offset within its word.
+    #[inline]
+    pub fn bit(self) -> u64 {
+        1u64 << (self.0 & 63)
+    }
+
+    /// Back to a half-index, given the segment's start.
+    #[inline]
+    pub fn global(self, seg_start: HalfIdx) -> HalfIdx {
+        seg_start.advance(self.0 as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bound_exhaustive_small() {
+        for n in 0..1_000u64 {
+            let h = half_index_bound(n);
+            let v = value_of_half_index(h);
+            if n >= 1 {
+                assert!(v <= n, "n = {}: bound {} stands for {}", n, h, v);
+            }
+            // The next half-index is always past n
+            assert!(value_of_half_index(h + 1) > n, "n = {}: bound {} stops short", n, h);
+        }
+        assert_eq!(half_index_bound(9), 4);
+        assert_eq!(half_index_bound(10), 4);
+        assert_eq!(half_index_bound(11), 5);
+        assert_eq!(half_index_bound(0), 0);
+        assert_eq!(half_index_bound(1), 0);
+        assert_eq!(half_index_bound(2), 0);
+        assert_eq!(half_index_bound(3), 1);
+    }
+
+    #[test]
+    fn test_value_round_trip_exhaustive_small() {
+        for v in 0..1_000u64 {
+            match half_index_of_value(v) {
+                Some(h) => {
+                    assert_eq!(v % 2, 1, "even {} got a half-index", v);
+                    assert_eq!(value_of_half_index(h), v);
+                }
+                None => assert_eq!(v % 2, 0, "odd {} has no half-index", v),
+            }
+        }
+        for h in 0..1_000u64 {
+            assert_eq!(half_index_of_value(value_of_half_index(h)), Some(h));
+        }
+        assert_eq!(half_index_of_value(2), None);
+        assert_eq!(half_index_of_value(1), Some(0));
+    }
+
+    #[test]
+    fn test_extremes() {
+        assert_eq!(half_index_bound(u64::MAX), (1 << 63) - 1);
+        assert_eq!(value_of_half_index(half_index_bound(u64::MAX)), u64::MAX);
+        assert_eq!(half_index_bound(u64::MAX - 1), (1 << 63) - 2);
+        assert_eq!(value_of_half_index(half_index_bound(u64::MAX - 1)), u64::MAX - 2);
+        assert_eq!(half_index_of_value(u64::MAX), Some((1 << 63) - 1));
+        assert_eq!(half_index_of_value(u64::MAX - 1), None);
+
+        // The sieves' cap, 2^63, is even: the last value sieved is 2^63 − 1
+        let cap = 1u64 << 63;
+        assert_eq!(value_of_half_index(half_index_bound(cap)), cap - 1);
+        assert_eq!(value_of_half_index(half_index_bound(cap + 1)), cap + 1);
+    }
+
+    #[test]
+    fn test_half_idx_even_values() {
+        for v in (1..1_000u64).step_by(2) {
+            assert_eq!(HalfIdx::of_value(v).map(HalfIdx::value), Some(v));
+            assert_eq!(HalfIdx::ceil(v).value(), v);
+        }
+        // Even values: rejected by of_value, rounded up by ceil
+        for v in (2..1_000u64).step_by(2) {
+            assert_eq!(HalfIdx::of_value(v), None, "v={}", v);
+            assert_eq!(HalfIdx::ceil(v).value(), v + 1, "v={}", v);
+        }
+        assert_eq!(HalfIdx::ceil(0), HalfIdx::ceil(1));
+        assert_eq!(HalfIdx::ceil(0).value(), 1);
+        assert_eq!(HalfIdx::ceil(u64::MAX).value(), u64::MAX);
+        // Bounds stop at the last odd value ≤ n: 9 for n = 10, never 11
+        assert_eq!(HalfIdx::bound(10).value(), 9);
+        assert_eq!(HalfIdx::bound(11).value(), 11);
+        assert_eq!(HalfIdx::bound(1 << 63).value(), (1 << 63) - 1);
+    }
+
+    #[test]
+    fn test_seg_offset_conversions() {
+        let seg_start = HalfIdx::ceil(3).advance(1 << 18);
+        let j = seg_start.advance(130);
+        let off = j.local(seg_start);
+        assert_eq!(off.index(), 130);
+        assert_eq!((off.word(), off.bit()), (2, 1 << 2));
+        assert_eq!(off, SegOffset::from_word_bit(2, 2));
+        assert_eq!(off.global(seg_start), j);
+        assert_eq!(seg_start.local(seg_start).index(), 0);
+    }
+
+    #[test]
+    fn test_first_at_or_after() {
+        // Odd multiples of 7 from 49: half-indices 24, 31, 38, ...
+        let p2 = HalfIdx::ceil(49);
+        assert_eq!(p2.first_at_or_after(HalfIdx::ceil(1), 7), p2);
+        assert_eq!(p2.first_at_or_after(p2, 7).value(), 49);
+        assert_eq!(p2.first_at_or_after(HalfIdx::ceil(51), 7).value(), 63);
+        assert_eq!(p2.first_at_or_after(HalfIdx::ceil(63), 7).value(), 63);
+        assert_eq!(p2.first_at_or_after(HalfIdx::ceil(64), 7).value(), 77);
+    }
+}
@@ -0,0 +1,218 @@
+//! A lazy prime iterator over the segmented sieve.
+//!
+//! `Primes` holds one segment buffer and the seed primes, and sieves the
+//! next segment only once the current one has been drained. Taking the
+//! first few primes past some point therefore costs a few segments, not a
+//! `Vec` of everything below it.
+
+use crate::flat::sieve_primes;
+use crate::half::{HalfIdx, SegOffset};
+use crate::segment::{segment_words, SEGMENT_BITS, SEGMENT_WORDS};
+use crate::segmented::strike_segment;
+use crate::{isqrt, prime_count_upper, MAX_N};
+
+/// Primes in ascending order, sieved one L1-sized segment at a time.
+///
+/// ```
+/// use primer_core::Primes;
+///
+/// let next: Vec<u64> = Primes::all().skip_while(|&p| p < 10_000_000).take(3).collect();
+/// assert_eq!(next, [10_000_019, 10_000_079, 10_000_103]);
+/// assert_eq!(Primes::up_to(100).count(), 25);
+/// ```
+pub struct Primes {
+    /// Largest value to yield.
+    limit: u64,
+    /// Half-index of `limit`'s last odd value.
+    h: HalfIdx,
+    /// Every odd prime ≤ `seed_limit`.
+    seeds: Vec<u64>,
+    seed_limit: u64,
+    seg: Vec<u64>,
+    /// Half-index of the current segment's first bit.
+    lo: HalfIdx,
+    /// Words the current segment filled, and the next one to drain.
+    words: usize,
+    word: usize,
+    /// What is left of word `word - 1`.
+    bits: u64,
+    /// Where the next segment starts; past `h` once the range is done.
+    next_lo: HalfIdx,
+    /// 2 lives outside the odd-only segments and is yielded first.
+    two: bool,
+    yielded: usize,
+}
+
+impl Primes {
+    /// Every prime ≤ `n`. The seed primes up to √n are sieved up front,
+    /// as `segmented_sieve` does. Panics if `n` exceeds `MAX_N` (2^63).
+    pub fn up_to(n: u64) -> Primes {
+        assert!(n <= MAX_N, "Primes::up_to: n = {} exceeds the supported maximum 2^63", n);
+        let mut primes = Primes::new(n, segment_words(HalfIdx::bound(n).local(HalfIdx::ceil(1)).index() as u64));
+        primes.extend_seeds(isqrt(n));
+        primes
+    }
+
+    /// Every prime, in practice: the iterator runs to `MAX_N` (2^63).
+    /// Seeds are sieved as the segments climb past their squares, so the
+    /// first primes come back after a single segment.
+    pub fn all() -> Primes {
+        Primes::new(MAX_N, SEGMENT_WORDS)
+    }
+
+    fn new(limit: u64, seg_words: usize) -> Primes {
+        Primes {
+            limit,
+            h: HalfIdx::bound(limit),
+            seeds: Vec::new(),
+            seed_limit: 0,
+            seg: vec![0u64; seg_words],
+            lo: HalfIdx::ceil(1),
+            words: 0,
+            word: 0,
+            bits: 0,
+            // Below 3 there are no odd primes to sieve for; start past h
+            next_lo: HalfIdx::ceil(if limit < 3 { 3 } else { 1 }),
+            two: limit >= 2,
+            yielded: 0,
+        }
+    }
+
+    /// Make sure the seeds cover every odd prime ≤ `need`. They grow at
+    /// least twofold each time, so an unbounded run re-sieves them only
+    /// O(log n) times.
+    fn extend_seeds(&mut self, need: u64) {
+        if need <= self.seed_limit { return; }
+        let limit = need.max(self.seed_limit.saturating_mul(2)).min(isqrt(self.limit));
+        self.seeds = sieve_primes(limit).into_iter().filter(|&p| p > 2).collect();
+        self.seed_limit = limit;
+    }
+
+    /// Sieve the next segment into the buffer; false once the range is done.
+    fn next_segment(&mut self) -> bool {
+        if self.next_lo > self.h { return false; }
+        let lo = self.next_lo;
+        let hi = lo.advance(SEGMENT_BITS - 1).min(self.h);
+        self.extend_seeds(isqrt(hi.value()));
+        self.words = strike_segment(&mut self.seg, lo, hi, &self.seeds);
+        self.lo = lo;
+        self.word = 0;
+        self.next_lo = lo.advance(SEGMENT_BITS);
+        true
+    }
+}
+
+/// Bounds on how many primes are ≤ `n`: x / ln x below (Rosser and
+/// Schoenfeld, x ≥ 17) and `prime_count_upper` above. That is only a true
+/// bound from 2,977 on, so smaller n use 1.25506 x / ln x instead.
+fn count_bounds(n: u64) -> (usize, usize) {
+    if n < 2 { return (0, 0); }
+    let x = n as f64;
+    let lower = if n >= 17 { (x / x.ln()) as usize } else { 0 };
+    let upper = if n >= 2_977 { prime_count_upper(n) } else { (1.25506 * x / x.ln()).ceil() as usize };
+    (lower, upper)
+}
+
+impl Iterator for Primes {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        if self.two {
+            self.two = false;
+            self.yielded += 1;
+            return Some(2);
+        }
+        loop {
+            // Brian Kernighan, one set bit per call
+            if self.bits != 0 {
+                let tz = self.bits.trailing_zeros();
+                self.bits &= self.bits - 1;
+                self.yielded += 1;
+                return Some(SegOffset::from_word_bit(self.word - 1, tz).global(self.lo).value());
+            }
+            if self.word < self.words {
+                self.bits = self.seg[self.word];
+                self.word += 1;
+            } else if !self.next_segment() {
+                return None;
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = count_bounds(self.limit);
+        (lower.saturating_sub(self.yielded), Some(upper.saturating_sub(self.yielded)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::segment::SEGMENT_SPAN;
+    use crate::segmented_sieve;
+
+    #[test]
+    fn test_up_to_matches_segmented() {
+        for n in 0..=2_000 {
+            assert_eq!(Primes::up_to(n).collect::<Vec<_>>(), segmented_sieve(n), "n={}", n);
+        }
+        for n in [1_100_000, 5_000_000] {
+            assert_eq!(Primes::up_to(n).collect::<Vec<_>>(), segmented_sieve(n), "n={}", n);
+        }
+    }
+
+    #[test]
+    fn test_up_to_across_segment_boundaries() {
+        // Limits one either side of a boundary, and one just into the third
+        // segment, where a drained segment hands over to the next
+        for n in [SEGMENT_SPAN - 1, SEGMENT_SPAN, SEGMENT_SPAN + 1, 2 * SEGMENT_SPAN + 1] {
+            assert_eq!(Primes::up_to(n).collect::<Vec<_>>(), segmented_sieve(n), "n={}", n);
+        }
+    }
+
+    #[test]
+    fn test_all_extends_seeds_as_it_climbs() {
+        // 5,000,000 spans ten segments, and the seeds needed grow from
+        // √524,287 to √5,000,000 along the way
+        let all: Vec<u64> = Primes::all().take_while(|&p| p <= 5_000_000).collect();
+        assert_eq!(all, segmented_sieve(5_000_000));
+    }
+
+    #[test]
+    fn test_all_is_lazy() {
+        // One segment's worth, so no seeds beyond √524,287 are needed
+        let mut primes = Primes::all();
+        assert_eq!(primes.nth(9_999), Some(104_729));
+        assert!(primes.seed_limit < 1_000, "seeds sieved to {}", primes.seed_limit);
+        assert_eq!(primes.seg.len(), SEGMENT_WORDS);
+    }
+
+    #[test]
+    fn test_size_hint_brackets_the_remaining_count() {
+        for n in (0..4_000).chain([65_537, 1_000_000]) {
+            let total = segmented_sieve(n).len();
+            let mut primes = Primes::up_to(n);
+            for left in (0..=total).rev() {
+                let (lower, upper) = primes.size_hint();
+                assert!(lower <= left && upper.is_some_and(|u| u >= left),
+                    "n={} with {} left: {:?}", n, left, (lower, upper));
+                primes.next();
+            }
+        }
+    }
+
+    #[test]
+    fn test_empty_and_tiny() {
+        assert_eq!(Primes::up_to(0).next(), None);
+        assert_eq!(Primes::up_to(1).next(), None);
+        assert_eq!(Primes::up_to(2).collect::<Vec<_>>(), vec![2]);
+        assert_eq!(Primes::up_to(3).collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!(Primes::up_to(0).size_hint(), (0, Some(0)));
+    }
+
+    #[test]
+    #[should_panic(expected = "exceeds the supported maximum")]
+    fn test_up_to_rejects_n_above_cap() {
+        Primes::up_to(MAX_N + 1);
+    }
+}
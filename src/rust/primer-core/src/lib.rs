@@ -0,0 +1,86 @@
+//! wofl's bit-packed Sieve of Eratosthenes, as a library.
+//!
+//! Two sieves over the same odd-only, bit-packed layout: `sieve_primes`
+//! keeps one flat bitmap for the whole range, `segmented_sieve` walks the
+//! range in L1-sized segments (`segment`) and is the one to use past a
+//! few million. Both return every prime ≤ n in ascending order;
+//! `sieve_range` runs the segmented sieve over a window [lo, hi] only, and
+//! `Primes` yields the same primes lazily, a segment at a time.
+//! `prime_pi` only counts them. `flat_atomic_sieve` is an experimental
+//! multi-threaded take on the flat sieve, kept for the benchmarks.
+//! `half` holds the value ↔ half-index conversions they share, and
+//! `is_admissible` / `densest_admissible_patterns` pick prime k-tuple
+//! patterns worth searching for.
+//!
+//! ```
+//! let primes = primer_core::segmented_sieve(100);
+//! assert_eq!(primes.len(), 25);
+//! assert_eq!(primes, primer_core::sieve_primes(100));
+//! ```
+
+mod atomic;
+mod flat;
+pub mod half;
+mod iter;
+pub mod segment;
+mod segmented;
+mod tiling;
+mod tuples;
+
+pub use atomic::{flat_atomic_sieve, flat_atomic_sieve_with, WorkerFailed};
+pub use flat::sieve_primes;
+pub use iter::Primes;
+pub use segmented::{prime_pi, segmented_sieve, segmented_sieve_with, sieve_range};
+pub use tiling::segmented_sieve_checked;
+pub use tuples::{densest_admissible_patterns, is_admissible};
+
+/// Largest n the segmented sieve accepts: 2^63. That keeps every
+/// half-index below 2^62, so segment ends, first-multiple offsets and
+/// 2·i + 1 never come near wrapping. Anything bigger is rejected up front
+/// rather than left to wrap somewhere inside the segment loop.
+pub const MAX_N: u64 = 1 << 63;
+
+/// Integer square root, ⌊√n⌋. Exact for every u64: the float estimate is
+/// corrected with overflow-checked squaring, so isqrt(2^32 ± 1) and
+/// isqrt(u64::MAX) = 2^32 − 1 come out right.
+#[inline]
+pub fn isqrt(n: u64) -> u64 {
+    if n == 0 { return 0; }
+    let mut x = (n as f64).sqrt() as u64;
+    while x > 0 && x.checked_mul(x).is_none_or(|sq| sq > n) { x -= 1; }
+    while (x + 1).checked_mul(x + 1).is_some_and(|sq| sq <= n) { x += 1; }
+    x
+}
+
+/// Upper bound on π(n) for pre-allocating the result, about 15% over.
+/// Strictly it only bounds π(n) from n = 2,977 on; between 73 and 2,976
+/// it can come up to 4 short, which costs one reallocation. Saturates at
+/// usize::MAX rather than wrapping on 32-bit targets, where it starts to
+/// matter near n = 2^36 (π(2^32) is only 203,280,221).
+#[inline]
+pub fn prime_count_upper(n: u64) -> usize {
+    if n < 10 { return 4; }
+    let nf = n as f64;
+    ((nf / nf.ln() * 1.15) as usize).saturating_add(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_isqrt_safety() {
+        assert_eq!(isqrt(0), 0);
+        assert_eq!(isqrt(1), 1);
+        assert_eq!(isqrt(99), 9);
+        assert_eq!(isqrt(100), 10);
+        assert_eq!(isqrt(u64::MAX), 4_294_967_295); // 2^32 - 1
+        assert_eq!(isqrt(MAX_N), 3_037_000_499);
+    }
+
+    #[test]
+    fn test_prime_count_upper_at_extremes() {
+        assert!(prime_count_upper(MAX_N) > 0);
+        assert_eq!(prime_count_upper(u64::MAX), prime_count_upper(u64::MAX - 1));
+    }
+}
@@ -0,0 +1,43 @@
+//! The L1 segment layout of the segmented sieve.
+//!
+//! Everything is derived from `SEGMENT_BYTES`, and the const checks below
+//! fail the build if a hand edit breaks a derivation.
+
+/// L1 cache segment size in bytes. 32KB is safe for virtually all x86/ARM;
+/// tune to L1d: x86_64 → 32KB, Apple M → 64KB, ESP32-S3 → 8KB.
+pub const SEGMENT_BYTES: usize = 32 * 1024;
+
+/// Bits per segment. Each bit stands for one odd number.
+pub const SEGMENT_BITS: u64 = SEGMENT_BYTES as u64 * 8;
+
+pub const SEGMENT_WORDS: usize = SEGMENT_BYTES / 8;
+
+/// Integers one segment spans: twice its bit count, since only odd numbers
+/// are stored.
+pub const SEGMENT_SPAN: u64 = SEGMENT_BITS * 2;
+
+const _: () = assert!(SEGMENT_BITS == SEGMENT_BYTES as u64 * 8);
+const _: () = assert!(SEGMENT_WORDS as u64 * 64 == SEGMENT_BITS);
+const _: () = assert!(SEGMENT_SPAN == 2 * SEGMENT_BITS);
+
+/// Words of segment buffer the sieve needs when the largest half-index is
+/// `h`: a full segment, or less when the whole range fits in one.
+pub fn segment_words(h: u64) -> usize {
+    (h / 64 + 1).min(SEGMENT_WORDS as u64) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::half::half_index_bound;
+    use crate::MAX_N;
+
+    #[test]
+    fn test_segment_words_clamped_to_range() {
+        assert_eq!(segment_words(0), 1);
+        assert_eq!(segment_words(5), 1);
+        assert_eq!(segment_words(64), 2);
+        assert_eq!(segment_words(SEGMENT_BITS - 1), SEGMENT_WORDS);
+        assert_eq!(segment_words(half_index_bound(MAX_N)), SEGMENT_WORDS);
+    }
+}
@@ -0,0 +1,451 @@
+//! wofl's SEGMENTED bit-packed sieve (L1-cache-friendly).
+//!
+//! Strategy:
+//!   1. Small sieve: find all primes up to √n (fits in a few KB)
+//!   2. Process the full range in L1-sized segments (~32KB = 262144 odd numbers)
+//!   3. For each segment, strike out composites using the small primes
+//!   4. Extract surviving primes via Brian Kernighan bit iteration
+//!
+//! The segment buffer stays hot in L1 cache the entire time, eliminating
+//! the cache thrashing that kills the flat sieve at large n.
+
+use crate::flat::sieve_primes;
+use crate::half::{HalfIdx, SegOffset};
+use crate::segment::{segment_words, SEGMENT_BITS};
+use crate::tiling::{check_tiling, SegmentSpan};
+use crate::{isqrt, prime_count_upper, MAX_N};
+
+/// The first odd multiple of `p` to strike in a segment starting at `lo`:
+/// p² itself, or the first multiple past it at or after lo. Stays in range
+/// for every lo up to the half-index of MAX_N.
+#[inline]
+pub(crate) fn first_strike(p: u64, lo: HalfIdx) -> HalfIdx {
+    // p² is odd, so ceil is its own half-index
+    HalfIdx::ceil(p * p).first_at_or_after(lo, p)
+}
+
+/// All primes ≤ `n`, sieved one L1-sized segment at a time, so memory is
+/// one segment plus the seed primes up to √n however large n is. The
+/// result is another matter: all 203,280,221 primes up to 2^32 take
+/// 1.6 GB as u64.
+///
+/// Panics if `n` exceeds `MAX_N` (2^63). There is no cliff at 2^32; see
+/// the ignored `test_counts_around_2_32`.
+pub fn segmented_sieve(n: u64) -> Vec<u64> {
+    assert!(n <= MAX_N, "segmented_sieve: n = {} exceeds the supported maximum 2^63", n);
+    sieve_window(0, n, SEGMENT_BITS, |_, _, _| {})
+}
+
+/// `segmented_sieve` with `segment_bits` bits per segment instead of
+/// `SEGMENT_BITS`, calling `on_segment(lo, hi, words)` once each segment's
+/// mask is final: half-indices [lo, hi] and the words holding them, with
+/// bits past hi cleared. For the seg bench's stress mode and tiling
+/// checks; not part of the supported API.
+///
+/// Panics if `n` exceeds `MAX_N` or `segment_bits` is not a positive
+/// multiple of 64.
+#[doc(hidden)]
+pub fn segmented_sieve_with<F>(n: u64, segment_bits: u64, on_segment: F) -> Vec<u64>
+where
+    F: FnMut(HalfIdx, HalfIdx, &[u64]),
+{
+    assert!(n <= MAX_N, "segmented_sieve: n = {} exceeds the supported maximum 2^63", n);
+    assert!(segment_bits > 0 && segment_bits.is_multiple_of(64),
+        "segmented_sieve: segment size {} is not a positive multiple of 64 bits", segment_bits);
+    sieve_window(0, n, segment_bits, on_segment)
+}
+
+/// All primes p with `lo` ≤ p ≤ `hi`, without sieving below `lo`: only
+/// the segments covering the window are processed, so the cost is the
+/// seed primes up to √hi plus the window itself. Empty when `hi < lo`.
+/// Windows may straddle 2^32 like any other value.
+///
+/// Panics if `hi` exceeds `MAX_N` (2^63).
+pub fn sieve_range(lo: u64, hi: u64) -> Vec<u64> {
+    assert!(hi <= MAX_N, "sieve_range: hi = {} exceeds the supported maximum 2^63", hi);
+    sieve_window(lo, hi, SEGMENT_BITS, |_, _, _| {})
+}
+
+/// π(n), the number of primes ≤ `n`: the segmented sieve with the
+/// extraction replaced by `count_ones` over each segment's words. Nothing
+/// is pushed, so memory is one segment plus the seed primes whatever n is.
+///
+/// Panics if `n` exceeds `MAX_N` (2^63).
+pub fn prime_pi(n: u64) -> u64 {
+    assert!(n <= MAX_N, "prime_pi: n = {} exceeds the supported maximum 2^63", n);
+    if n < 2 { return 0; }
+
+    let h = HalfIdx::bound(n);
+    let small_odd_primes: Vec<u64> = sieve_primes(isqrt(n)).into_iter()
+        .filter(|&p| p > 2)
+        .collect();
+
+    let mut lo = HalfIdx::ceil(1);
+    let mut seg = vec![0u64; segment_words(h.local(lo).index() as u64)];
+    let mut count = 1; // 2
+    while lo <= h {
+        let hi = lo.advance(SEGMENT_BITS - 1).min(h);
+        // strike_segment clears the bits past hi, so every set bit counts
+        let words_needed = strike_segment(&mut seg, lo, hi, &small_odd_primes);
+        count += seg[..words_needed].iter().map(|w| w.count_ones() as u64).sum::<u64>();
+        lo = lo.advance(SEGMENT_BITS);
+    }
+    count
+}
+
+/// Capacity hint for the primes in [lo, hi]: the window over ln(lo), about
+/// 15% over like `prime_count_upper`, and never more than that bound.
+fn window_count_upper(lo: u64, hi: u64) -> usize {
+    if lo < 10 { return prime_count_upper(hi); }
+    let estimate = ((hi - lo + 1) as f64 / (lo as f64).ln() * 1.15) as usize;
+    estimate.saturating_add(1).min(prime_count_upper(hi))
+}
+
+/// Sieve the segment of half-indices [lo, hi] into `seg`: afterwards a bit
+/// is set exactly when its odd value is prime, provided `seeds` holds every
+/// odd prime up to √(value of hi). Returns the words the segment filled;
+/// bits past `hi` in the last one are cleared.
+pub(crate) fn strike_segment(seg: &mut [u64], lo: HalfIdx, hi: HalfIdx, seeds: &[u64]) -> usize {
+    let seg_len = hi.local(lo).index() + 1; // actual bits in this segment
+    let words_needed = seg_len.div_ceil(64);
+
+    // Reset segment: all bits = 1 (assume prime)
+    for w in seg[..words_needed].iter_mut() {
+        *w = !0u64;
+    }
+
+    // Special case: bit 0 of first segment represents 1 (not prime)
+    if lo == HalfIdx::ceil(1) {
+        seg[0] ^= 1;
+    }
+
+    // Strike composites for each sieving prime
+    for &p in seeds {
+        // First composite half-index ≥ lo: the smallest odd multiple of
+        // p that's ≥ max(p*p, 2*lo+1)
+        let mut j = first_strike(p, lo);
+        while j <= hi {
+            let off = j.local(lo);
+            seg[off.word()] &= !off.bit();
+            j = j.advance(p);
+        }
+    }
+
+    // Mask off any trailing bits beyond hi; only the last segment is short
+    if !seg_len.is_multiple_of(64) {
+        let valid_bits = seg_len % 64;
+        seg[words_needed - 1] &= (1u64 << valid_bits) - 1;
+    }
+
+    words_needed
+}
+
+/// The segment loop behind all three: primes in [first, n], in segments
+/// of `segment_bits` bits, each handed to `on_segment` before extraction.
+/// Debug builds also check that the segments tiled the window.
+fn sieve_window<F>(first: u64, n: u64, segment_bits: u64, mut on_segment: F) -> Vec<u64>
+where
+    F: FnMut(HalfIdx, HalfIdx, &[u64]),
+{
+    if n < 2 || n < first { return vec![]; }
+
+    let sqrt_n = isqrt(n);
+    let h = HalfIdx::bound(n); // last half-index with value ≤ n
+    // First half-index with value ≥ first. 0 and 1 both start at bit 0,
+    // the number 1.
+    let start = HalfIdx::ceil(first);
+
+    // ── Phase 1: small sieve to find primes ≤ √n ──────────────────────
+    // These are the "sieving primes" that we'll use to mark composites
+    // in each segment. For n=50M, √n ≈ 7071, so this is tiny.
+    // Skip prime=2 since we only track odd numbers
+    let small_odd_primes: Vec<u64> = sieve_primes(sqrt_n).into_iter()
+        .filter(|&p| p > 2)
+        .collect();
+
+    // ── Phase 2: process segments ──────────────────────────────────────
+    let mut result = Vec::with_capacity(window_count_upper(first, n));
+    if first <= 2 {
+        result.push(2);
+    }
+    // [2, 2] has no odd values to sieve
+    if start > h {
+        return result;
+    }
+
+    // Segment buffer — reused across all segments, stays in L1, and no
+    // bigger than the window when that is smaller than one segment
+    let range_words = h.local(start).index() / 64 + 1;
+    let mut seg = vec![0u64; range_words.min((segment_bits / 64) as usize)];
+
+    // Process in chunks of segment_bits half-indices, from the window start
+    let mut lo = start; // current segment start (in half-index space)
+    let mut spans = Vec::new();
+
+    while lo <= h {
+        let hi = lo.advance(segment_bits - 1).min(h); // inclusive end
+        let words_needed = strike_segment(&mut seg, lo, hi, &small_odd_primes);
+        on_segment(lo, hi, &seg[..words_needed]);
+        if cfg!(debug_assertions) {
+            spans.push(SegmentSpan::new(lo, hi, &seg[..words_needed]));
+        }
+
+        // Extract primes from this segment
+        for (wi, &word) in seg[..words_needed].iter().enumerate() {
+            let mut w = word;
+            while w != 0 {
+                // The offset stays small; only the global half-index is u64
+                let p = SegOffset::from_word_bit(wi, w.trailing_zeros()).global(lo).value();
+                if p <= n {
+                    result.push(p);
+                }
+                w &= w - 1;
+            }
+        }
+
+        lo = lo.advance(segment_bits);
+    }
+
+    if cfg!(debug_assertions) {
+        if let Err(e) = check_tiling(&spans, start, h) {
+            panic!("segmented sieve over [{}, {}]: {}", first, n, e);
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Trial division, sharing nothing with either sieve.
+    fn naive_primes(n: u64) -> Vec<u64> {
+        (2..=n).filter(|&k| (2..k).take_while(|d| d * d <= k).all(|d| k % d != 0)).collect()
+    }
+
+    #[test]
+    fn test_known_counts() {
+        assert_eq!(segmented_sieve(10), vec![2, 3, 5, 7]);
+        assert_eq!(segmented_sieve(100).len(), 25);
+        assert_eq!(segmented_sieve(10_000).len(), 1_229);
+        assert_eq!(segmented_sieve(500_000).len(), 41_538);
+        assert_eq!(segmented_sieve(1_000_000).len(), 78_498);
+        assert_eq!(segmented_sieve(10_000_000).len(), 664_579);
+    }
+
+    #[test]
+    #[should_panic(expected = "exceeds the supported maximum")]
+    fn test_rejects_n_above_cap() {
+        segmented_sieve(MAX_N + 1);
+    }
+
+    #[test]
+    fn test_first_strike_near_cap() {
+        // Largest seed the cap allows (the largest prime ≤ √(2^63)) against
+        // segment starts at the very top of the half-index range.
+        let p = 3_037_000_493;
+        let top = HalfIdx::bound(MAX_N);
+        for lo in [top.value() - 2 * SEGMENT_BITS, top.value() - 2 * p, top.value() - 2, top.value()] {
+            let lo = HalfIdx::ceil(lo);
+            let j = first_strike(p, lo);
+            assert!(j >= lo && (j.local(lo).index() as u64) < p, "lo={:?}", lo);
+            assert_eq!(j.value() % p, 0, "lo={:?}", lo);
+        }
+        // Below p² the first strike is p² itself
+        assert_eq!(first_strike(7, HalfIdx::ceil(1)).value(), 49);
+        assert_eq!(first_strike(7, HalfIdx::ceil(49)).value(), 49);
+        assert_eq!(first_strike(7, HalfIdx::ceil(51)).value(), 63);
+    }
+
+    #[test]
+    fn test_matches_flat_small_n() {
+        for n in 0..=1_000 {
+            let seg = segmented_sieve(n);
+            assert_eq!(seg, sieve_primes(n), "n={}", n);
+            assert!(seg.iter().filter(|&&p| p == 2).count() <= 1, "n={}", n);
+        }
+    }
+
+    #[test]
+    fn test_matches_naive() {
+        for n in 0..=2_000 {
+            assert_eq!(segmented_sieve(n), naive_primes(n), "n={}", n);
+        }
+        for n in [2 * SEGMENT_BITS, 2 * SEGMENT_BITS + 1] {
+            assert_eq!(segmented_sieve(n), naive_primes(n), "n={}", n);
+        }
+    }
+
+    #[test]
+    fn test_matches_flat_across_segments() {
+        // 1,100,000 straddles the second segment boundary (~1,048,576)
+        for n in [1_100_000, 5_000_000] {
+            assert_eq!(segmented_sieve(n), sieve_primes(n), "n={}", n);
+        }
+    }
+
+    #[test]
+    fn test_segment_size_does_not_change_output() {
+        for bits in [64, 128, 4_096] {
+            for n in [0, 2, 3, 127, 128, 129, 10_007, 100_000] {
+                let mut spans = 0;
+                let primes = segmented_sieve_with(n, bits, |lo, hi, words| {
+                    assert!(hi.local(lo).index() < bits as usize && words.len() <= (bits / 64) as usize);
+                    spans += 1;
+                });
+                assert_eq!(primes, segmented_sieve(n), "bits={} n={}", bits, n);
+                let half_indices = if n < 2 { 0 } else { n.div_ceil(2) };
+                assert_eq!(spans, half_indices.div_ceil(bits), "bits={} n={}", bits, n);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "not a positive multiple of 64")]
+    fn test_rejects_odd_segment_size() {
+        segmented_sieve_with(1_000, 100, |_, _, _| {});
+    }
+
+    #[test]
+    fn test_prime_pi_known_counts() {
+        assert_eq!(prime_pi(100), 25);
+        assert_eq!(prime_pi(1_000), 168);
+        assert_eq!(prime_pi(10_000), 1_229);
+        assert_eq!(prime_pi(500_000), 41_538);
+        assert_eq!(prime_pi(1_000_000), 78_498);
+        assert_eq!(prime_pi(10_000_000), 664_579);
+        assert_eq!(prime_pi(100_000_000), 5_761_455);
+    }
+
+    #[test]
+    #[ignore = "sieves all of [0, 10^9]; run with --ignored (release: about a second)"]
+    fn test_prime_pi_10_9() {
+        assert_eq!(prime_pi(1_000_000_000), 50_847_534);
+    }
+
+    #[test]
+    fn test_prime_pi_matches_segmented() {
+        for n in 0..=2_000 {
+            assert_eq!(prime_pi(n), segmented_sieve(n).len() as u64, "n={}", n);
+        }
+        // Short last words and segment boundaries, where the tail mask matters
+        for n in [2 * SEGMENT_BITS - 1, 2 * SEGMENT_BITS, 2 * SEGMENT_BITS + 1, 2 * SEGMENT_BITS + 127,
+                  1_100_000, 5_000_000] {
+            assert_eq!(prime_pi(n), segmented_sieve(n).len() as u64, "n={}", n);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "exceeds the supported maximum")]
+    fn test_prime_pi_rejects_n_above_cap() {
+        prime_pi(MAX_N + 1);
+    }
+
+    #[test]
+    fn test_range_small_windows_exhaustive() {
+        let all = naive_primes(300);
+        for lo in 0..=300u64 {
+            for hi in lo.saturating_sub(3)..=300 {
+                let expected: Vec<u64> = all.iter().copied().filter(|&p| lo <= p && p <= hi).collect();
+                assert_eq!(sieve_range(lo, hi), expected, "[{}, {}]", lo, hi);
+            }
+        }
+    }
+
+    #[test]
+    fn test_range_edges() {
+        assert_eq!(sieve_range(0, 10), vec![2, 3, 5, 7]);
+        assert_eq!(sieve_range(2, 2), vec![2]);
+        assert_eq!(sieve_range(3, 3), vec![3]);
+        // Even and odd starts, and a start that is itself prime
+        assert_eq!(sieve_range(10, 30), vec![11, 13, 17, 19, 23, 29]);
+        assert_eq!(sieve_range(11, 30), vec![11, 13, 17, 19, 23, 29]);
+        assert_eq!(sieve_range(24, 28), vec![]);
+        assert_eq!(sieve_range(30, 10), vec![]);
+        assert_eq!(sieve_range(5, 0), vec![]);
+    }
+
+    #[test]
+    fn test_range_matches_flat_across_segments() {
+        let flat = sieve_primes(3_000_000);
+        let span = 2 * SEGMENT_BITS;
+        // Windows that start mid-segment and cross one or more boundaries,
+        // plus seeds whose p² lies past the window start
+        for (lo, hi) in [(1_000, 1_100_000), (span - 10, span + 10), (span - 1, 2 * span + 1),
+                         (500_001, 2_999_999), (2_000_000, 3_000_000)] {
+            let expected: Vec<u64> = flat.iter().copied().filter(|&p| lo <= p && p <= hi).collect();
+            assert_eq!(sieve_range(lo, hi), expected, "[{}, {}]", lo, hi);
+        }
+    }
+
+    #[test]
+    fn test_range_high_window() {
+        // Nothing below 10^10 is sieved. Trial division over every odd k
+        // in the window (10^10 itself is even) checks what came back.
+        let (lo, hi) = (10_000_000_000, 10_000_010_000);
+        let primes = sieve_range(lo, hi);
+        let is_prime = |k: u64| (3..).step_by(2).take_while(|d| d * d <= k).all(|d| !k.is_multiple_of(d));
+        assert!(primes.iter().all(|&p| (lo..=hi).contains(&p) && is_prime(p)));
+        let count = (lo + 1..=hi).step_by(2).filter(|&k| is_prime(k)).count();
+        assert_eq!(primes.len(), count);
+    }
+
+    #[test]
+    #[should_panic(expected = "exceeds the supported maximum")]
+    fn test_range_rejects_hi_above_cap() {
+        sieve_range(MAX_N, MAX_N + 1);
+    }
+
+    /// π(2^32), pinned.
+    const PI_2_32: usize = 203_280_221;
+
+    /// π(n) for n up to a few billion, counted window by window so the
+    /// result vectors stay small.
+    fn pi_by_windows(n: u64, window: u64) -> usize {
+        let mut count = 0;
+        let mut lo = 0;
+        while lo <= n {
+            let hi = (lo + window - 1).min(n);
+            count += sieve_range(lo, hi).len();
+            lo = hi + 1;
+        }
+        count
+    }
+
+    #[test]
+    #[ignore = "sieves all of [0, 2^32]; run with --ignored (release: a few seconds)"]
+    fn test_counts_around_2_32() {
+        const TWO_32: u64 = 1 << 32;
+        assert_eq!(pi_by_windows(TWO_32, 1 << 26), PI_2_32);
+        assert_eq!(prime_pi(TWO_32), PI_2_32 as u64);
+
+        // The primes on either side of 2^32, found without a full count
+        let below = sieve_range(TWO_32 - 100, TWO_32);
+        let above = sieve_range(TWO_32, TWO_32 + 100);
+        assert_eq!(below.last(), Some(&4_294_967_291));
+        assert_eq!(above.first(), Some(&4_294_967_311));
+
+        // π at each n follows from π(2^32) and a window between them
+        let pi = |n: u64| if n <= TWO_32 {
+            PI_2_32 - sieve_range(n + 1, TWO_32).len()
+        } else {
+            PI_2_32 + sieve_range(TWO_32 + 1, n).len()
+        };
+        for n in [TWO_32 - 2, TWO_32 - 1, TWO_32, TWO_32 + 1] {
+            assert_eq!(pi(n), PI_2_32, "n={}", n);
+        }
+        let far = TWO_32 + 1_000_000;
+        // A second count at a different window size, so the segment
+        // boundaries fall elsewhere
+        assert_eq!(pi_by_windows(far, 100_000_007), pi(far));
+
+        // The window itself, one segment at a time vs in small pieces
+        let window = sieve_range(TWO_32 - 1_000_000, far);
+        let pieces: Vec<u64> = (0..2_000).flat_map(|k| {
+            let lo = TWO_32 - 1_000_000 + k * 1_000;
+            sieve_range(lo, lo + 999)
+        }).chain(sieve_range(far, far)).collect();
+        assert_eq!(window, pieces);
+    }
+}
@@ -0,0 +1,168 @@
+//! Checking that the segments tile the sieved range.
+//!
+//! Each segment reports the half-indices it covered and the lowest and
+//! highest bit left set in its mask. The segments must cover the range in
+//! order, with no gap and no overlap, and no segment may leave a bit set
+//! outside its own span. Debug builds check every segmented run this way;
+//! `segmented_sieve_checked` does it in any build, at any segment size.
+
+use crate::half::{HalfIdx, SegOffset};
+use crate::segmented::segmented_sieve_with;
+
+/// Half-index range one segment covered, plus the lowest and highest
+/// half-index left set in its mask — the span extraction could emit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct SegmentSpan {
+    pub(crate) start: HalfIdx,
+    pub(crate) end: HalfIdx,
+    pub(crate) extracted: Option<(HalfIdx, HalfIdx)>,
+}
+
+impl SegmentSpan {
+    /// The span of the segment [start, end] whose mask is `words`.
+    pub(crate) fn new(start: HalfIdx, end: HalfIdx, words: &[u64]) -> SegmentSpan {
+        SegmentSpan { start, end, extracted: set_bit_span(words, start) }
+    }
+}
+
+/// Lowest and highest set bit of a segment mask, as half-indices.
+fn set_bit_span(words: &[u64], seg_start: HalfIdx) -> Option<(HalfIdx, HalfIdx)> {
+    let lo_word = words.iter().position(|&w| w != 0)?;
+    let hi_word = words.iter().rposition(|&w| w != 0)?;
+    let lo = SegOffset::from_word_bit(lo_word, words[lo_word].trailing_zeros());
+    let hi = SegOffset::from_word_bit(hi_word, 63 - words[hi_word].leading_zeros());
+    Some((lo.global(seg_start), hi.global(seg_start)))
+}
+
+/// Check that `spans` tile [first, last] exactly: in order, with no gap and
+/// no overlap, and with nothing left set outside its own segment.
+pub(crate) fn check_tiling(spans: &[SegmentSpan], first: HalfIdx, last: HalfIdx) -> Result<(), String> {
+    let mut expected = first;
+    for (k, s) in spans.iter().enumerate() {
+        if s.start > expected {
+            return Err(format!("gap before segment {}: values {}..{} never sieved",
+                k, expected.value(), s.start.value()));
+        }
+        if s.start < expected {
+            return Err(format!("segment {} overlaps its predecessor: starts at value {}, expected {}",
+                k, s.start.value(), expected.value()));
+        }
+        if s.end < s.start {
+            return Err(format!("segment {} is empty: ends at value {} before its start {}",
+                k, s.end.value(), s.start.value()));
+        }
+        if let Some((lo, hi)) = s.extracted {
+            if lo < s.start || hi > s.end {
+                return Err(format!("segment {} covers values {}..={} but its mask reaches {}..={}",
+                    k, s.start.value(), s.end.value(), lo.value(), hi.value()));
+            }
+        }
+        expected = s.end.advance(1);
+    }
+    if expected != last.advance(1) {
+        return Err(format!("segments stop before value {}, expected to reach {}",
+            expected.value(), last.value()));
+    }
+    Ok(())
+}
+
+/// `segmented_sieve_with` at `segment_bits` bits per segment, checking
+/// that the segments tiled [1, n] whatever the build; returns the primes
+/// and the number of segments. For the seg bench's stress mode; not part
+/// of the supported API.
+#[doc(hidden)]
+pub fn segmented_sieve_checked(n: u64, segment_bits: u64) -> Result<(Vec<u64>, usize), String> {
+    let mut spans = Vec::new();
+    let primes = segmented_sieve_with(n, segment_bits, |start, end, words| {
+        spans.push(SegmentSpan::new(start, end, words));
+    });
+    if n >= 2 {
+        check_tiling(&spans, HalfIdx::ceil(1), HalfIdx::bound(n))?;
+    }
+    Ok((primes, spans.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sieve_primes;
+
+    #[test]
+    fn test_tiny_segments_tile_exactly() {
+        // 64- and 128-bit segments force hundreds of boundaries at small n.
+        for bits in [64, 128, 192] {
+            for n in [3, 4, 127, 128, 129, 130, 257, 1_000, 10_007, 65_536] {
+                let (primes, _) = segmented_sieve_checked(n, bits)
+                    .unwrap_or_else(|e| panic!("bits={} n={}: {}", bits, n, e));
+                assert_eq!(primes, sieve_primes(n), "bits={} n={}", bits, n);
+            }
+        }
+    }
+
+    #[test]
+    fn test_stress_segment_sizes_match_flat() {
+        // The seg bench's --stress-segments sizes: 512 B, 1000 B (not a
+        // power of two) and 1 KiB
+        for bytes in [512, 1_000, 1_024] {
+            for n in [3, 1_000, 65_537, 1_000_000] {
+                let (primes, segments) = segmented_sieve_checked(n, bytes * 8)
+                    .unwrap_or_else(|e| panic!("{} B n={}: {}", bytes, n, e));
+                assert_eq!(primes, sieve_primes(n), "{} B n={}", bytes, n);
+                assert_eq!(segments as u64, n.div_ceil(2).div_ceil(bytes * 8), "{} B n={}", bytes, n);
+            }
+        }
+    }
+
+    #[test]
+    fn test_big_step_overshoot_stays_bounded() {
+        // A single tiny segment where most seeds' first strike lies past
+        // the end of the range.
+        for n in [3, 10, 48, 49, 50, 63, 64, 65] {
+            let (primes, _) = segmented_sieve_checked(n, 64).unwrap_or_else(|e| panic!("n={}: {}", n, e));
+            assert_eq!(primes, sieve_primes(n), "n={}", n);
+        }
+    }
+
+    /// Spans from a real run at 64-bit segments, with `perturb` applied to
+    /// segment `k` as it is reported.
+    fn perturbed_spans(k: usize, perturb: impl Fn(&mut SegmentSpan)) -> Vec<SegmentSpan> {
+        let mut spans = Vec::new();
+        segmented_sieve_with(2_000, 64, |start, end, words| {
+            let mut s = SegmentSpan::new(start, end, words);
+            if spans.len() == k { perturb(&mut s); }
+            spans.push(s);
+        });
+        spans
+    }
+
+    #[test]
+    fn test_tiling_check_fires_on_perturbed_boundary() {
+        let (first, last) = (HalfIdx::ceil(1), HalfIdx::bound(2_000));
+        assert_eq!(check_tiling(&perturbed_spans(0, |_| {}), first, last), Ok(()));
+
+        let shrunk_end = perturbed_spans(3, |s| s.end = HalfIdx::ceil(s.end.value() - 2));
+        let err = check_tiling(&shrunk_end, first, last).unwrap_err();
+        assert!(err.starts_with("gap before segment 4"), "{}", err);
+
+        let early_start = perturbed_spans(5, |s| s.start = HalfIdx::ceil(s.start.value() - 2));
+        let err = check_tiling(&early_start, first, last).unwrap_err();
+        assert!(err.starts_with("segment 5 overlaps"), "{}", err);
+
+        let leaked = perturbed_spans(2, |s| s.extracted = Some((s.start, s.end.advance(1))));
+        let err = check_tiling(&leaked, first, last).unwrap_err();
+        assert!(err.contains("segment 2 covers"), "{}", err);
+
+        let mut truncated = perturbed_spans(0, |_| {});
+        truncated.pop();
+        let err = check_tiling(&truncated, first, last).unwrap_err();
+        assert!(err.starts_with("segments stop before"), "{}", err);
+    }
+
+    #[test]
+    fn test_set_bit_span() {
+        let start = HalfIdx::ceil(3);
+        assert_eq!(set_bit_span(&[0, 0], start), None);
+        let span = set_bit_span(&[0, 0b100, 1 << 63, 0], start).unwrap();
+        assert_eq!(span, (start.advance(66), start.advance(191)));
+    }
+}
@@ -0,0 +1,120 @@
+//! Admissible prime k-tuple patterns, for picking constellations to hunt.
+//!
+//! A pattern of offsets (0, 2, 6, …) is admissible when, for every prime
+//! p, some residue mod p is missed by all of its offsets; otherwise one
+//! of n + offset is always divisible by p and the pattern can hold primes
+//! at most finitely often. Only primes p ≤ k need checking, since k
+//! offsets can't cover more than k residues.
+
+use crate::sieve_primes;
+
+/// Whether `pattern` is admissible: its offsets leave some residue free
+/// mod every prime ≤ its length. Offsets need not be sorted or start at 0.
+/// (0, 2, 4) is not (it covers 0, 2 and 1 mod 3); (0, 2, 6) is.
+pub fn is_admissible(pattern: &[u64]) -> bool {
+    misses_a_residue(pattern, &sieve_primes(pattern.len() as u64))
+}
+
+/// Whether the offsets leave a residue free mod each of `primes`.
+fn misses_a_residue(pattern: &[u64], primes: &[u64]) -> bool {
+    primes.iter().all(|&p| {
+        let mut covered = vec![false; p as usize];
+        for &offset in pattern {
+            covered[(offset % p) as usize] = true;
+        }
+        covered.contains(&false)
+    })
+}
+
+/// Every admissible k-offset pattern of the smallest width w ≤ `width`,
+/// as sorted offsets from 0 to w, in lexicographic order. Empty when no
+/// admissible k-pattern fits in `width`.
+///
+/// Offsets are placed left to right, and a partial pattern that already
+/// covers every residue mod some prime ≤ k is dropped with everything
+/// that would extend it. That keeps small k (up to a dozen or so) cheap.
+/// A pattern and its mirror image (w − each offset) are both returned.
+pub fn densest_admissible_patterns(k: usize, width: u64) -> Vec<Vec<u64>> {
+    if k <= 1 { return vec![vec![0; k]]; }
+    let primes = sieve_primes(k as u64);
+    for w in 1..=width {
+        let mut found = Vec::new();
+        extend_pattern(&mut vec![0], k, w, &primes, &mut found);
+        if !found.is_empty() { return found; }
+    }
+    Vec::new()
+}
+
+/// Depth-first over the interior offsets of k-patterns spanning [0, w].
+fn extend_pattern(pattern: &mut Vec<u64>, k: usize, w: u64, primes: &[u64], found: &mut Vec<Vec<u64>>) {
+    if pattern.len() == k - 1 {
+        pattern.push(w);
+        if misses_a_residue(pattern, primes) { found.push(pattern.clone()); }
+        pattern.pop();
+        return;
+    }
+    // Leave room below w for the interior offsets still to come
+    let still_to_place = (k - 1 - pattern.len()) as u64;
+    let last = pattern[pattern.len() - 1];
+    for next in last + 1..=w.saturating_sub(still_to_place) {
+        pattern.push(next);
+        if misses_a_residue(pattern, primes) {
+            extend_pattern(pattern, k, w, primes, found);
+        }
+        pattern.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_admissible() {
+        assert!(!is_admissible(&[0, 2, 4]));
+        assert!(is_admissible(&[0, 2, 6]));
+        assert!(is_admissible(&[0, 4, 6]));
+        assert!(!is_admissible(&[0, 1]));
+        assert!(is_admissible(&[0, 2, 6, 8]));
+        // Order and a common shift don't matter
+        assert!(is_admissible(&[8, 0, 6, 2]));
+        assert!(is_admissible(&[100, 102, 106]));
+        assert!(!is_admissible(&[4, 2, 0]));
+        assert!(is_admissible(&[]));
+        assert!(is_admissible(&[0]));
+    }
+
+    #[test]
+    fn test_densest_known_constellations() {
+        assert_eq!(densest_admissible_patterns(2, 10), vec![vec![0, 2]]);
+        assert_eq!(densest_admissible_patterns(3, 10), vec![vec![0, 2, 6], vec![0, 4, 6]]);
+        assert_eq!(densest_admissible_patterns(4, 20), vec![vec![0, 2, 6, 8]]);
+        assert_eq!(densest_admissible_patterns(5, 20), vec![vec![0, 2, 6, 8, 12], vec![0, 4, 6, 10, 12]]);
+        assert_eq!(densest_admissible_patterns(6, 20), vec![vec![0, 4, 6, 10, 12, 16]]);
+        assert_eq!(densest_admissible_patterns(7, 30),
+            vec![vec![0, 2, 6, 8, 12, 18, 20], vec![0, 2, 8, 12, 14, 18, 20]]);
+    }
+
+    #[test]
+    fn test_densest_width_too_small() {
+        assert_eq!(densest_admissible_patterns(4, 7), Vec::<Vec<u64>>::new());
+        assert_eq!(densest_admissible_patterns(2, 1), Vec::<Vec<u64>>::new());
+        assert_eq!(densest_admissible_patterns(1, 0), vec![vec![0]]);
+        assert_eq!(densest_admissible_patterns(0, 0), vec![Vec::<u64>::new()]);
+    }
+
+    #[test]
+    fn test_densest_results_admissible_and_mirrored() {
+        for k in 2..=8 {
+            let patterns = densest_admissible_patterns(k, 40);
+            assert!(!patterns.is_empty(), "k={}", k);
+            for pattern in &patterns {
+                assert!(is_admissible(pattern), "{:?}", pattern);
+                let w = pattern[k - 1];
+                let mut mirror: Vec<u64> = pattern.iter().map(|&o| w - o).collect();
+                mirror.reverse();
+                assert!(patterns.contains(&mirror), "{:?}", pattern);
+            }
+        }
+    }
+}